@@ -0,0 +1,32 @@
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+
+/// Replaces each `@file` argument with the arguments listed in the file, one per line.
+pub(crate) fn expand_args(args: impl IntoIterator<Item = OsString>) -> io::Result<Vec<OsString>> {
+    let mut args = args.into_iter();
+    let mut result = Vec::new();
+
+    // The program name is never expanded
+    result.extend(args.next());
+
+    for arg in args {
+        match arg.to_str().and_then(|arg| arg.strip_prefix('@')) {
+            Some(path) if !path.is_empty() => {
+                let contents = fs::read_to_string(path).map_err(|err| {
+                    io::Error::new(err.kind(), format!("could not read {}: {}", path, err))
+                })?;
+
+                result.extend(
+                    contents
+                        .lines()
+                        .filter(|line| !line.is_empty())
+                        .map(OsString::from),
+                );
+            }
+            _ => result.push(arg),
+        }
+    }
+
+    Ok(result)
+}
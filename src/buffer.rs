@@ -0,0 +1,58 @@
+use std::cell::RefCell;
+
+/// Size of the chunks read from files.
+pub(crate) const CHUNK_SIZE: usize = 8192;
+
+/// Largest chunk size accepted when reading the end of files backwards.
+pub(crate) const MAX_TAIL_CHUNK: usize = 16 * 1024 * 1024;
+
+thread_local! {
+    static BUFFER: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Lends the calling thread's read buffer, grown to at least the given size, so that
+/// processing files doesn't allocate a new buffer each time.
+///
+/// Calls must not be nested: `f` must not call `with_buffer` again, directly or not.
+pub(crate) fn with_buffer<R>(size: usize, f: impl FnOnce(&mut [u8]) -> R) -> R {
+    BUFFER.with(|buffer| {
+        debug_assert!(
+            buffer.try_borrow_mut().is_ok(),
+            "with_buffer calls must not be nested"
+        );
+        let mut buffer = buffer.borrow_mut();
+        if buffer.len() < size {
+            buffer.resize(size, 0);
+        }
+        f(&mut buffer[..size])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reuses_the_allocation() {
+        let first = with_buffer(CHUNK_SIZE, |buf| buf.as_ptr());
+        let second = with_buffer(CHUNK_SIZE / 2, |buf| buf.as_ptr());
+        let third = with_buffer(CHUNK_SIZE, |buf| buf.as_ptr());
+
+        assert_eq!(first, second);
+        assert_eq!(first, third);
+    }
+
+    #[test]
+    fn grows_to_the_requested_size() {
+        with_buffer(1, |_| {});
+        assert_eq!(with_buffer(CHUNK_SIZE * 2, |buf| buf.len()), CHUNK_SIZE * 2);
+        assert_eq!(with_buffer(1, |buf| buf.len()), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "must not be nested")]
+    #[cfg(debug_assertions)]
+    fn refuses_nested_calls() {
+        with_buffer(1, |_| with_buffer(1, |_| {}));
+    }
+}
@@ -0,0 +1,39 @@
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, LineWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Appends the paths of the processed files to a file, one per line, so that an
+/// interrupted run can be resumed. Each line is written as soon as it's complete.
+pub(crate) struct Checkpoint {
+    out: LineWriter<File>,
+}
+
+impl Checkpoint {
+    pub(crate) fn open(path: &Path) -> io::Result<Checkpoint> {
+        let file = File::options().create(true).append(true).open(path)?;
+        Ok(Checkpoint {
+            out: LineWriter::new(file),
+        })
+    }
+
+    pub(crate) fn record(&mut self, path: &Path) -> io::Result<()> {
+        writeln!(self.out, "{}", path.to_string_lossy())
+    }
+}
+
+/// Reads the paths recorded in a checkpoint.
+pub(crate) fn read(path: &Path) -> io::Result<HashSet<PathBuf>> {
+    let contents = fs::read_to_string(path).map_err(|err| {
+        io::Error::new(
+            err.kind(),
+            format!("could not read checkpoint {}: {}", path.display(), err),
+        )
+    })?;
+
+    Ok(contents
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect())
+}
@@ -0,0 +1,117 @@
+/// Content of a file after its line endings were normalized.
+pub(crate) struct Normalized {
+    pub content: Vec<u8>,
+    pub removed: u64,
+    pub added: u64,
+}
+
+impl From<&[u8]> for Normalized {
+    fn from(content: &[u8]) -> Normalized {
+        Normalized {
+            content: content.to_vec(),
+            removed: 0,
+            added: 0,
+        }
+    }
+}
+
+/// Removes the spaces and tabs at the end of every line, and makes sure the content ends
+/// with an end-of-line, using the given one if it's missing.
+pub(crate) fn strip_trailing_whitespace(content: &[u8], eol: &[u8]) -> Normalized {
+    let mut result = Normalized {
+        content: Vec::with_capacity(content.len() + eol.len()),
+        removed: 0,
+        added: 0,
+    };
+
+    for line in content.split_inclusive(|&b| b == b'\n') {
+        let (text, terminator) = split_terminator(line);
+        let trimmed = trim_end(text);
+
+        result.removed += (text.len() - trimmed.len()) as u64;
+        result.content.extend_from_slice(trimmed);
+        result.content.extend_from_slice(terminator);
+    }
+
+    match result.content.last() {
+        None | Some(b'\n') => {}
+        Some(b'\r') if eol == b"\r\n" => {
+            result.content.push(b'\n');
+            result.added += 1;
+        }
+        Some(b'\r') => {
+            result.content.pop();
+            result.content.extend_from_slice(eol);
+            result.removed += 1;
+            result.added += eol.len() as u64;
+        }
+        Some(_) => {
+            result.content.extend_from_slice(eol);
+            result.added += eol.len() as u64;
+        }
+    }
+
+    result
+}
+
+/// Splits a line into its text and its end-of-line, a carriage return at the end of the
+/// content being considered as an end-of-line.
+fn split_terminator(line: &[u8]) -> (&[u8], &[u8]) {
+    let len = match line {
+        [.., b'\r', b'\n'] => 2,
+        [.., b'\n'] | [.., b'\r'] => 1,
+        _ => 0,
+    };
+
+    line.split_at(line.len() - len)
+}
+
+fn trim_end(text: &[u8]) -> &[u8] {
+    let len = text
+        .iter()
+        .rposition(|&b| b != b' ' && b != b'\t')
+        .map_or(0, |index| index + 1);
+
+    &text[..len]
+}
+
+/// Converts every line ending (LF, CRLF, or a carriage return ending the file) to the
+/// given end-of-line, and makes sure the content ends with it. Carriage returns in the
+/// middle of lines are kept as is.
+pub(crate) fn normalize(content: &[u8], eol: &[u8]) -> Normalized {
+    let mut result = Normalized {
+        content: Vec::with_capacity(content.len() + content.len() / 32 + eol.len()),
+        removed: 0,
+        added: 0,
+    };
+
+    let mut index = 0;
+    while index < content.len() {
+        let terminator_len = match content[index] {
+            b'\n' => 1,
+            b'\r' if content.get(index + 1) == Some(&b'\n') => 2,
+            b'\r' if index + 1 == content.len() => 1,
+            byte => {
+                result.content.push(byte);
+                index += 1;
+                continue;
+            }
+        };
+
+        let terminator = &content[index..index + terminator_len];
+        if terminator != eol {
+            result.removed += terminator_len as u64;
+            result.added += eol.len() as u64;
+        }
+
+        result.content.extend_from_slice(eol);
+        index += terminator_len;
+    }
+
+    if !result.content.ends_with(eol) {
+        result.content.extend_from_slice(eol);
+        result.added += eol.len() as u64;
+    }
+
+    result
+}
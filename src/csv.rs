@@ -0,0 +1,142 @@
+use crate::paths::PathSeparator;
+use crate::printer::path_bytes;
+use crate::{eol, FileResult};
+use std::io::{self, Write};
+
+pub(crate) fn write_header(out: &mut impl Write, delimiter: u8) -> io::Result<()> {
+    write_row(
+        out,
+        delimiter,
+        &[b"path", b"status", b"bytes_added", b"eol"],
+    )
+}
+
+/// Writes a row for each result concerning a path.
+pub(crate) fn write_result(
+    out: &mut impl Write,
+    delimiter: u8,
+    result: &FileResult,
+    separator: PathSeparator,
+) -> io::Result<()> {
+    let Some(path) = result.path() else {
+        return Ok(());
+    };
+
+    let added = result
+        .change()
+        .map_or(String::new(), |change| change.added.to_string());
+    let eol = result
+        .detected()
+        .and_then(|detected| detected.eol)
+        .map_or("", eol::name);
+
+    write_row(
+        out,
+        delimiter,
+        &[
+            &path_bytes(&separator.apply(path)),
+            result.status().as_bytes(),
+            added.as_bytes(),
+            eol.as_bytes(),
+        ],
+    )?;
+    out.flush()
+}
+
+fn write_row(out: &mut impl Write, delimiter: u8, fields: &[&[u8]]) -> io::Result<()> {
+    for (index, field) in fields.iter().enumerate() {
+        if index != 0 {
+            out.write_all(&[delimiter])?;
+        }
+        write_field(out, delimiter, field)?;
+    }
+
+    // RFC 4180 mandates CRLF, while TSV is usually consumed by line-oriented tools
+    out.write_all(if delimiter == b'\t' { b"\n" } else { b"\r\n" })
+}
+
+/// Writes a field, quoted as described in RFC 4180 when it contains the delimiter,
+/// a quote or a line break.
+fn write_field(out: &mut impl Write, delimiter: u8, field: &[u8]) -> io::Result<()> {
+    let needs_quoting = field
+        .iter()
+        .any(|&b| b == delimiter || b == b'"' || b == b'\r' || b == b'\n');
+
+    if !needs_quoting {
+        return out.write_all(field);
+    }
+
+    out.write_all(b"\"")?;
+    for part in field.split_inclusive(|&b| b == b'"') {
+        out.write_all(part)?;
+        if part.ends_with(b"\"") {
+            out.write_all(b"\"")?;
+        }
+    }
+    out.write_all(b"\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn row(delimiter: u8, fields: &[&[u8]]) -> String {
+        let mut out = Vec::new();
+        write_row(&mut out, delimiter, fields).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn leaves_plain_fields_unquoted() {
+        assert_eq!(
+            row(b',', &[b"a.txt", b"updated", b""]),
+            "a.txt,updated,\r\n"
+        );
+        assert_eq!(row(b'\t', &[b"a b.txt", b"updated"]), "a b.txt\tupdated\n");
+    }
+
+    #[test]
+    fn quotes_fields_containing_the_delimiter() {
+        assert_eq!(row(b',', &[b"a,b.txt", b"x"]), "\"a,b.txt\",x\r\n");
+        assert_eq!(row(b'\t', &[b"a\tb.txt", b"x"]), "\"a\tb.txt\"\tx\n");
+        assert_eq!(row(b'\t', &[b"a,b.txt", b"x"]), "a,b.txt\tx\n");
+    }
+
+    #[test]
+    fn doubles_quotes() {
+        assert_eq!(row(b',', &[b"a\"b\".txt"]), "\"a\"\"b\"\".txt\"\r\n");
+        assert_eq!(row(b',', &[b"\""]), "\"\"\"\"\r\n");
+    }
+
+    #[test]
+    fn quotes_fields_containing_line_breaks() {
+        assert_eq!(row(b',', &[b"a\nb.txt"]), "\"a\nb.txt\"\r\n");
+        assert_eq!(row(b',', &[b"a\rb.txt"]), "\"a\rb.txt\"\r\n");
+    }
+
+    #[test]
+    fn writes_results_with_their_path() {
+        let mut out = Vec::new();
+        write_header(&mut out, b',').unwrap();
+        write_result(
+            &mut out,
+            b',',
+            &FileResult::LongFinalLine(PathBuf::from("dir/a,b.txt"), 100),
+            PathSeparator::Native,
+        )
+        .unwrap();
+        write_result(
+            &mut out,
+            b',',
+            &FileResult::WalkError(None, "failed".into()),
+            PathSeparator::Native,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "path,status,bytes_added,eol\r\n\"dir/a,b.txt\",long-line,,\r\n"
+        );
+    }
+}
@@ -0,0 +1,74 @@
+use crate::printer::path_bytes;
+use crate::{Change, LastLine};
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Writes a unified diff of the change made to the last line of a file.
+/// Binary or non UTF-8 content, whole file rewrites and custom terminators are omitted, with a note
+/// which patch tools ignore.
+pub(crate) fn write_diff(out: &mut impl Write, path: &Path, change: &Change) -> io::Result<()> {
+    let path = path.strip_prefix(".").unwrap_or(path);
+    let path = path_bytes(path);
+
+    let Some(ref last_line) = change.last_line else {
+        out.write_all(b"Rewritten file ")?;
+        out.write_all(&path)?;
+        return out.write_all(b" omitted from the diff\n");
+    };
+
+    if last_line.before.contains(&0) || std::str::from_utf8(&last_line.before).is_err() {
+        out.write_all(b"Binary or non UTF-8 file ")?;
+        out.write_all(&path)?;
+        return out.write_all(b" omitted from the diff\n");
+    }
+
+    write_hunk(out, &path, last_line)
+}
+
+/// Writes a patch which makes the change to the last line of a file when given to
+/// `git apply`. Files which can't be shown in a diff are left out entirely.
+pub(crate) fn write_git_patch(
+    out: &mut impl Write,
+    path: &Path,
+    change: &Change,
+) -> io::Result<()> {
+    let path = path.strip_prefix(".").unwrap_or(path);
+    let path = path_bytes(path);
+
+    let Some(ref last_line) = change.last_line else {
+        return Ok(());
+    };
+
+    if last_line.before.contains(&0) || std::str::from_utf8(&last_line.before).is_err() {
+        return Ok(());
+    }
+
+    out.write_all(b"diff --git a/")?;
+    out.write_all(&path)?;
+    out.write_all(b" b/")?;
+    out.write_all(&path)?;
+    out.write_all(b"\n")?;
+
+    write_hunk(out, &path, last_line)
+}
+
+fn write_hunk(out: &mut impl Write, path: &[u8], last_line: &LastLine) -> io::Result<()> {
+    out.write_all(b"--- a/")?;
+    out.write_all(path)?;
+    out.write_all(b"\n+++ b/")?;
+    out.write_all(path)?;
+
+    if last_line.before.is_empty() {
+        // Empty file
+        out.write_all(b"\n@@ -0,0 +1 @@\n")?;
+    } else {
+        writeln!(out, "\n@@ -{0} +{0} @@", last_line.number)?;
+
+        out.write_all(b"-")?;
+        out.write_all(&last_line.before)?;
+        out.write_all(b"\n\\ No newline at end of file\n")?;
+    }
+
+    out.write_all(b"+")?;
+    out.write_all(&last_line.after)
+}
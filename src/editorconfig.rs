@@ -0,0 +1,89 @@
+use crate::{eol, glob, paths};
+use ignore::overrides::OverrideBuilder;
+use std::fs;
+use std::path::Path;
+
+const FILE_NAME: &str = ".editorconfig";
+
+/// Returns the end-of-line given to the file by the `end_of_line` property of the
+/// `.editorconfig` files of its directory and of the parent ones.
+///
+/// Closer files take precedence, and the search stops at a file declaring `root = true`.
+/// Within a file, the last matching section wins.
+pub(crate) fn eol(path: &Path) -> Option<&'static [u8]> {
+    let path = paths::absolute(path).ok()?;
+    let mut configs = Vec::new();
+
+    for dir in path.ancestors().skip(1) {
+        let Ok(contents) = fs::read_to_string(dir.join(FILE_NAME)) else {
+            continue;
+        };
+
+        let is_root = is_root(&contents);
+        configs.push((dir, contents));
+        if is_root {
+            break;
+        }
+    }
+
+    configs
+        .iter()
+        .find_map(|(dir, contents)| end_of_line(contents, dir, &path))
+}
+
+/// Returns whether the preamble, before the first section, declares `root = true`.
+fn is_root(contents: &str) -> bool {
+    properties(contents)
+        .take_while(|(section, _, _)| section.is_none())
+        .any(|(_, key, value)| key == "root" && value.eq_ignore_ascii_case("true"))
+}
+
+/// Returns the end-of-line set for the file by the last matching section.
+fn end_of_line(contents: &str, dir: &Path, path: &Path) -> Option<&'static [u8]> {
+    properties(contents)
+        .filter(|(_, key, _)| *key == "end_of_line")
+        .filter(|(section, _, _)| section.is_some_and(|section| matches(section, dir, path)))
+        .filter_map(|(_, _, value)| match value.to_ascii_lowercase().as_str() {
+            "lf" => Some(eol::LF),
+            "crlf" => Some(eol::CRLF),
+            _ => None,
+        })
+        .last()
+}
+
+/// Iterates over the properties of the file, with the section they belong to.
+fn properties(contents: &str) -> impl Iterator<Item = (Option<&str>, &str, &str)> {
+    let mut section = None;
+
+    contents.lines().filter_map(move |line| {
+        let line = line.trim();
+
+        if line.starts_with('[') && line.ends_with(']') {
+            section = Some(&line[1..line.len() - 1]);
+            return None;
+        }
+
+        if line.starts_with('#') || line.starts_with(';') {
+            return None;
+        }
+
+        let (key, value) = line.split_once('=')?;
+        Some((section, key.trim(), value.trim()))
+    })
+}
+
+/// Matches a section name against the file, like a gitignore pattern relative to the
+/// directory of the `.editorconfig` file.
+fn matches(section: &str, dir: &Path, path: &Path) -> bool {
+    let mut builder = OverrideBuilder::new(dir);
+
+    for pattern in glob::expand_braces(section) {
+        if builder.add(&pattern).is_err() {
+            return false;
+        }
+    }
+
+    builder
+        .build()
+        .is_ok_and(|globs| globs.matched(path, false).is_whitelist())
+}
@@ -0,0 +1,41 @@
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// Byte order marks, longest first so that UTF-32 LE isn't mistaken for UTF-16 LE.
+const BOMS: &[(&[u8], &str)] = &[
+    (b"\xEF\xBB\xBF", "utf-8-bom"),
+    (b"\xFF\xFE\x00\x00", "utf-32le"),
+    (b"\x00\x00\xFE\xFF", "utf-32be"),
+    (b"\xFF\xFE", "utf-16le"),
+    (b"\xFE\xFF", "utf-16be"),
+];
+
+/// Length of the longest byte order mark.
+pub(crate) const MAX_BOM_LEN: u64 = 4;
+
+/// Returns the name of the encoding indicated by the byte order mark at the start of
+/// the content, or `utf-8` if there is none.
+pub(crate) fn detect(content: &[u8]) -> &'static str {
+    BOMS.iter()
+        .find(|(bom, _)| content.starts_with(bom))
+        .map_or("utf-8", |&(_, name)| name)
+}
+
+/// Returns whether the content is nothing but a byte order mark.
+pub(crate) fn is_bom_only(content: &[u8]) -> bool {
+    BOMS.iter().any(|&(bom, _)| content == bom)
+}
+
+/// Returns whether the encoding name denotes content starting with a byte order mark.
+pub(crate) fn has_bom(name: &str) -> bool {
+    name != "utf-8"
+}
+
+/// Detects the encoding from the byte order mark at the start of the file.
+pub(crate) fn detect_file(file: &mut (impl Read + Seek)) -> io::Result<&'static str> {
+    file.seek(SeekFrom::Start(0))?;
+
+    let mut prefix = Vec::with_capacity(4);
+    file.take(MAX_BOM_LEN).read_to_end(&mut prefix)?;
+
+    Ok(detect(&prefix))
+}
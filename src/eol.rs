@@ -0,0 +1,226 @@
+use crate::buffer;
+use clap::ValueEnum;
+use std::io::{self, Read, Seek, SeekFrom};
+
+#[cfg(windows)]
+pub(crate) const NATIVE: &[u8] = b"\r\n";
+#[cfg(not(windows))]
+pub(crate) const NATIVE: &[u8] = b"\n";
+
+pub(crate) const LF: &[u8] = b"\n";
+pub(crate) const CRLF: &[u8] = b"\r\n";
+
+/// U+2028 and U+2029 encoded in UTF-8.
+pub(crate) const LINE_SEPARATOR: &[u8] = "\u{2028}".as_bytes();
+pub(crate) const PARAGRAPH_SEPARATOR: &[u8] = "\u{2029}".as_bytes();
+
+/// Maximum number of bytes of a custom terminator.
+pub(crate) const MAX_SEQUENCE_LEN: usize = 8;
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Eol {
+    /// The platform's end-of-line
+    Native,
+    /// Unix-style line feed
+    Lf,
+    /// Windows-style carriage return and line feed
+    Crlf,
+    /// The first end-of-line found in the file
+    Auto,
+}
+
+impl Eol {
+    /// Returns the end-of-line to use for the given file, or `None` if it can't be
+    /// detected in auto mode.
+    pub(crate) fn resolve(
+        self,
+        file: &mut (impl Read + Seek),
+    ) -> io::Result<Option<&'static [u8]>> {
+        Ok(match self {
+            Eol::Native => Some(NATIVE),
+            Eol::Lf => Some(LF),
+            Eol::Crlf => Some(CRLF),
+            Eol::Auto => detect(file)?,
+        })
+    }
+}
+
+/// End-of-line used when none can be detected nor is configured for the file.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Fallback {
+    /// The platform's end-of-line
+    Native,
+    /// Unix-style line feed
+    Lf,
+    /// Windows-style carriage return and line feed
+    Crlf,
+}
+
+impl Fallback {
+    pub(crate) fn bytes(self) -> &'static [u8] {
+        match self {
+            Fallback::Native => NATIVE,
+            Fallback::Lf => LF,
+            Fallback::Crlf => CRLF,
+        }
+    }
+}
+
+/// Returns the name of the given end-of-line, as used in machine-readable output.
+pub(crate) fn name(eol: &[u8]) -> &'static str {
+    match eol {
+        CRLF => "crlf",
+        LF => "lf",
+        _ => "custom",
+    }
+}
+
+/// Parses a custom terminator given as hexadecimal bytes, such as `0D0A`.
+///
+/// The result lives until the end of the program, like the built-in end-of-lines.
+pub(crate) fn parse_sequence(hex: &str) -> Result<&'static [u8], String> {
+    let hex = hex.trim_start_matches("0x");
+
+    if hex.is_empty() || !hex.len().is_multiple_of(2) {
+        return Err("expected an even number of hexadecimal digits".into());
+    }
+
+    if hex.len() / 2 > MAX_SEQUENCE_LEN {
+        return Err(format!("can't be longer than {} bytes", MAX_SEQUENCE_LEN));
+    }
+
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|index| {
+            hex.get(index..index + 2)
+                .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                .ok_or_else(|| format!("invalid hexadecimal byte at position {}", index + 1))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Box::leak(bytes.into_boxed_slice()))
+}
+
+/// Returns whether the file of the given length ends with the given bytes.
+pub(crate) fn ends_with(
+    file: &mut (impl Read + Seek),
+    len: u64,
+    sequence: &[u8],
+) -> io::Result<bool> {
+    if len < sequence.len() as u64 {
+        return Ok(false);
+    }
+
+    let mut tail = vec![0u8; sequence.len()];
+    file.seek(SeekFrom::Start(len - sequence.len() as u64))?;
+    file.read_exact(&mut tail)?;
+
+    Ok(tail == sequence)
+}
+
+/// Returns whether the first `len` bytes of the file end with a Unicode line or paragraph
+/// separator, given the last of these bytes.
+pub(crate) fn ends_with_unicode_separator(
+    file: &mut (impl Read + Seek),
+    len: u64,
+    last_byte: u8,
+) -> io::Result<bool> {
+    if last_byte != LINE_SEPARATOR[2] && last_byte != PARAGRAPH_SEPARATOR[2] {
+        return Ok(false);
+    }
+
+    if len < LINE_SEPARATOR.len() as u64 {
+        return Ok(false);
+    }
+
+    file.seek(SeekFrom::Start(len - LINE_SEPARATOR.len() as u64))?;
+
+    let mut tail = [0u8; 3];
+    file.read_exact(&mut tail)?;
+
+    Ok(tail == LINE_SEPARATOR || tail == PARAGRAPH_SEPARATOR)
+}
+
+/// Returns the offset at which the trailing spaces, tabs and line endings of the file
+/// start, by reading it backwards in chunks of the given size.
+pub(crate) fn trailing_whitespace_start(
+    file: &mut (impl Read + Seek),
+    len: u64,
+    chunk_size: usize,
+) -> io::Result<u64> {
+    // Not nested: the scan only reads chunks of the file
+    buffer::with_buffer(chunk_size, |buf| {
+        let mut pos = len;
+
+        while pos > 0 {
+            let chunk_len = pos.min(buf.len() as u64) as usize;
+            let chunk_start = pos - chunk_len as u64;
+
+            file.seek(SeekFrom::Start(chunk_start))?;
+            file.read_exact(&mut buf[..chunk_len])?;
+
+            match buf[..chunk_len]
+                .iter()
+                .rposition(|&b| !matches!(b, b' ' | b'\t' | b'\r' | b'\n'))
+            {
+                Some(index) => return Ok(chunk_start + index as u64 + 1),
+                None => pos = chunk_start,
+            }
+        }
+
+        Ok(0)
+    })
+}
+
+/// Looks for the first line feed in the file, and returns the end-of-line it belongs to.
+pub(crate) fn detect(file: &mut (impl Read + Seek)) -> io::Result<Option<&'static [u8]>> {
+    file.seek(SeekFrom::Start(0))?;
+
+    // Not nested: detecting the end-of-line only reads the file
+    buffer::with_buffer(buffer::CHUNK_SIZE, |buf| {
+        let mut previous = 0u8;
+
+        loop {
+            let len = match file.read(buf) {
+                Ok(0) => return Ok(None),
+                Ok(len) => len,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+
+            if let Some(index) = buf[..len].iter().position(|&b| b == b'\n') {
+                let before = if index == 0 { previous } else { buf[index - 1] };
+                return Ok(Some(if before == b'\r' { CRLF } else { LF }));
+            }
+
+            previous = buf[len - 1];
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn ends_with_separator(content: &[u8]) -> bool {
+        let last_byte = content.last().copied().unwrap_or_default();
+        ends_with_unicode_separator(&mut Cursor::new(content), content.len() as u64, last_byte)
+            .unwrap()
+    }
+
+    #[test]
+    fn detects_unicode_separators() {
+        assert!(ends_with_separator("line\u{2028}".as_bytes()));
+        assert!(ends_with_separator("line\u{2029}".as_bytes()));
+        assert!(ends_with_separator("\u{2028}".as_bytes()));
+    }
+
+    #[test]
+    fn ignores_other_endings() {
+        assert!(!ends_with_separator(b"line\n"));
+        assert!(!ends_with_separator("line\u{2027}".as_bytes()));
+        assert!(!ends_with_separator("\u{2028}x".as_bytes()));
+        assert!(!ends_with_separator(&LINE_SEPARATOR[1..]));
+    }
+}
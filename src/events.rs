@@ -0,0 +1,107 @@
+use crate::paths::PathSeparator;
+use crate::report::Report;
+use crate::{encoding, eol, json, Args, FileResult};
+use clap::ValueEnum;
+use std::io::{self, Write};
+use std::time::Duration;
+
+/// Version of the event stream schema, to be bumped on incompatible changes.
+const SCHEMA_VERSION: u32 = 1;
+
+/// Writes the start event, with the globs read from the command line and the glob files.
+pub(crate) fn write_start(out: &mut impl Write, args: &Args, globs: &[String]) -> io::Result<()> {
+    write!(
+        out,
+        "{{\"event\":\"start\",\"version\":{},\"dry_run\":{},\"eol\":",
+        SCHEMA_VERSION, args.dry_run
+    )?;
+    json::write_str(out, value_name(&args.eol))?;
+    write!(out, ",\"globs\":")?;
+    json::write_str_array(out, globs)?;
+    write!(out, ",\"extensions\":")?;
+    json::write_str_array(out, &args.ext)?;
+    write!(out, ",\"only_extensionless\":{}", args.only_extensionless)?;
+    write!(out, ",\"roots\":")?;
+    json::write_str_array(out, &args.paths)?;
+    writeln!(out, "}}")?;
+    out.flush()
+}
+
+pub(crate) fn write_file(
+    out: &mut impl Write,
+    result: &FileResult,
+    duration: Option<Duration>,
+    separator: PathSeparator,
+) -> io::Result<()> {
+    write!(out, "{{\"event\":\"file\",\"status\":")?;
+    json::write_str(out, result.status())?;
+
+    if let Some(path) = result.path() {
+        write!(out, ",\"path\":")?;
+        json::write_str(out, &separator.apply(path).to_string_lossy())?;
+    }
+
+    if let Some(detected) = result.detected() {
+        write!(out, ",\"encoding\":")?;
+        json::write_str(out, detected.encoding)?;
+        write!(out, ",\"bom\":{}", encoding::has_bom(detected.encoding))?;
+        if let Some(newline) = detected.eol {
+            write!(out, ",\"eol\":")?;
+            json::write_str(out, eol::name(newline))?;
+        }
+    }
+
+    if let Some(err) = result.error() {
+        write!(out, ",\"error\":")?;
+        json::write_str(out, &err.to_string())?;
+    }
+
+    if let Some(duration) = duration {
+        write!(out, ",\"duration_us\":{}", duration.as_micros())?;
+    }
+
+    writeln!(out, "}}")?;
+    out.flush()
+}
+
+pub(crate) fn write_end(out: &mut impl Write, report: &Report) -> io::Result<()> {
+    writeln!(
+        out,
+        "{{\"event\":\"end\",\"total_files\":{},\"updated_files\":{},\"errors\":{},\"skipped_symlinks\":{},\"elapsed_ms\":{}}}",
+        report.file_count,
+        report.updated_count,
+        report.error_count,
+        report.symlink_count,
+        report.elapsed.as_millis()
+    )?;
+    out.flush()
+}
+
+fn value_name(value: &impl ValueEnum) -> &'static str {
+    value
+        .to_possible_value()
+        .map_or("", |value| value.get_name())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Cli;
+    use clap::Parser;
+
+    #[test]
+    fn start_event_lists_the_file_selection() {
+        let args = Cli::parse_from(["addeol", "--glob", "*.rs", "--ext", "md", "src"]).into_args();
+        let globs = ["*.rs".to_string(), "*.toml".to_string()];
+
+        let mut out = Vec::new();
+        write_start(&mut out, &args, &globs).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"event\":\"start\",\"version\":1,\"dry_run\":false,\"eol\":\"native\",\
+             \"globs\":[\"*.rs\",\"*.toml\"],\"extensions\":[\"md\"],\
+             \"only_extensionless\":false,\"roots\":[\"src\"]}\n"
+        );
+    }
+}
@@ -0,0 +1,149 @@
+use crate::printer::path_from_bytes;
+use crate::{eol, Result};
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Mutex, OnceLock};
+
+fn git<I, S>(args: I) -> Result<Vec<u8>>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let output = Command::new("git")
+        .args(args)
+        .output()
+        .map_err(|err| format!("could not run git: {}", err))?;
+
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().into());
+    }
+
+    Ok(output.stdout)
+}
+
+/// Returns the root of the enclosing git working tree.
+pub(crate) fn toplevel() -> Result<PathBuf> {
+    let output =
+        git(["rev-parse", "--show-toplevel"]).map_err(|_| "not inside a git repository")?;
+
+    Ok(path_from_bytes(trim_newline(&output)))
+}
+
+/// Returns the absolute paths of the existing files which differ from the given reference.
+pub(crate) fn changed_files(reference: &str) -> Result<Vec<PathBuf>> {
+    let toplevel = toplevel()?;
+
+    git([
+        "rev-parse",
+        "--verify",
+        "--quiet",
+        &format!("{}^{{commit}}", reference),
+    ])
+    .map_err(|_| format!("unknown git reference: {}", reference))?;
+
+    let output = git([
+        OsStr::new("-C"),
+        toplevel.as_os_str(),
+        OsStr::new("diff"),
+        OsStr::new("--name-only"),
+        OsStr::new("-z"),
+        OsStr::new("--diff-filter=d"),
+        OsStr::new(reference),
+        OsStr::new("--"),
+    ])?;
+
+    Ok(output
+        .split(|&b| b == 0)
+        .filter(|path| !path.is_empty())
+        .map(|path| toplevel.join(path_from_bytes(path)))
+        .collect())
+}
+
+/// Returns the absolute paths of the existing files tracked in the enclosing working tree.
+pub(crate) fn tracked_files() -> Result<Vec<PathBuf>> {
+    let toplevel = toplevel()?;
+
+    let output = git([
+        OsStr::new("-C"),
+        toplevel.as_os_str(),
+        OsStr::new("ls-files"),
+        OsStr::new("-z"),
+        OsStr::new("--"),
+    ])?;
+
+    // Files deleted from the working tree, and submodules, are listed too
+    Ok(output
+        .split(|&b| b == 0)
+        .filter(|path| !path.is_empty())
+        .map(|path| toplevel.join(path_from_bytes(path)))
+        .filter(|path| path.is_file())
+        .collect())
+}
+
+type ConfigCache = Mutex<HashMap<PathBuf, Option<&'static [u8]>>>;
+
+/// Returns the end-of-line git would use in the working tree for the given file,
+/// based on its `eol` attribute, then on the `core.autocrlf` and `core.eol` settings.
+/// Returns `None` when the file is not in a repository or nothing is configured.
+pub(crate) fn working_tree_eol(path: &Path) -> Option<&'static [u8]> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    let attr = git([
+        OsStr::new("-C"),
+        dir.as_os_str(),
+        OsStr::new("check-attr"),
+        OsStr::new("eol"),
+        OsStr::new("--"),
+        path.file_name()?,
+    ])
+    .ok()?;
+
+    match attr.rsplit(|&b| b == b' ').next().map(trim_newline) {
+        Some(b"lf") => return Some(eol::LF),
+        Some(b"crlf") => return Some(eol::CRLF),
+        _ => {}
+    }
+
+    static CONFIG_CACHE: OnceLock<ConfigCache> = OnceLock::new();
+
+    let mut cache = CONFIG_CACHE.get_or_init(Default::default).lock().unwrap();
+    *cache
+        .entry(dir.to_path_buf())
+        .or_insert_with(|| configured_eol(dir))
+}
+
+fn configured_eol(dir: &Path) -> Option<&'static [u8]> {
+    let config = |name: &str| {
+        git([
+            OsStr::new("-C"),
+            dir.as_os_str(),
+            OsStr::new("config"),
+            OsStr::new("--get"),
+            OsStr::new(name),
+        ])
+        .ok()
+        .map(|value| trim_newline(&value).to_ascii_lowercase())
+    };
+
+    match config("core.autocrlf").as_deref() {
+        Some(b"true") => return Some(eol::CRLF),
+        Some(b"input") => return Some(eol::LF),
+        _ => {}
+    }
+
+    match config("core.eol").as_deref() {
+        Some(b"lf") => Some(eol::LF),
+        Some(b"crlf") => Some(eol::CRLF),
+        Some(b"native") => Some(eol::NATIVE),
+        _ => None,
+    }
+}
+
+fn trim_newline(bytes: &[u8]) -> &[u8] {
+    bytes.strip_suffix(b"\n").unwrap_or(bytes)
+}
@@ -0,0 +1,266 @@
+use ignore::overrides::{Override, OverrideBuilder};
+use std::path::Path;
+use std::{fs, io, iter};
+
+/// Keeps track of the globs which matched at least one file.
+pub(crate) struct MatchTracker {
+    globs: Vec<(String, Override)>,
+    matched: Vec<bool>,
+}
+
+impl MatchTracker {
+    /// Tracks the given globs, relative to the given directory. Negated globs exclude files,
+    /// so they're not tracked.
+    pub(crate) fn new(
+        globs: &[String],
+        root: &Path,
+        case_insensitive: bool,
+    ) -> Result<MatchTracker, ignore::Error> {
+        let globs = globs
+            .iter()
+            .filter(|glob| !glob.starts_with('!'))
+            .map(|glob| {
+                let mut builder = OverrideBuilder::new(root);
+                builder.case_insensitive(case_insensitive)?;
+                for pattern in patterns(glob) {
+                    builder.add(&pattern)?;
+                }
+                Ok((glob.clone(), builder.build()?))
+            })
+            .collect::<Result<Vec<_>, ignore::Error>>()?;
+
+        Ok(MatchTracker {
+            matched: vec![false; globs.len()],
+            globs,
+        })
+    }
+
+    pub(crate) fn record(&mut self, path: &Path) {
+        for ((_, glob), matched) in self.globs.iter().zip(&mut self.matched) {
+            if !*matched && glob.matched(path, false).is_whitelist() {
+                *matched = true;
+            }
+        }
+    }
+
+    /// Returns the globs which didn't match any of the recorded files.
+    pub(crate) fn unmatched(&self) -> impl Iterator<Item = &str> {
+        self.globs
+            .iter()
+            .zip(&self.matched)
+            .filter(|(_, &matched)| !matched)
+            .map(|((glob, _), _)| glob.as_str())
+    }
+}
+
+/// Returns the patterns to give to the override builder for a glob given on the command line.
+///
+/// Like in gitignore files, a glob starting with a slash, or containing one elsewhere than
+/// at its end, is anchored to the current directory. A leading `./` anchors the glob the
+/// same way, where the override builder would otherwise match it against nothing.
+pub(crate) fn patterns(glob: &str) -> Vec<String> {
+    let (negation, pattern) = match glob.strip_prefix('!') {
+        Some(pattern) => ("!", pattern),
+        None => ("", glob),
+    };
+
+    match pattern.strip_prefix("./") {
+        Some(anchored) => expand_braces(&format!("{}/{}", negation, anchored)),
+        None => expand_braces(glob),
+    }
+}
+
+/// Expands brace alternatives such as `*.{rs,toml}` into separate patterns.
+///
+/// Nested groups are expanded recursively, and escaped characters (`\{`, `\}`, `\,`)
+/// are left untouched. Unbalanced braces are kept as literals.
+pub(crate) fn expand_braces(pattern: &str) -> Vec<String> {
+    let mut result = Vec::new();
+    expand_into(pattern, &mut result);
+    result
+}
+
+/// Reads globs from a file, one per line, ignoring blank lines and `#` comments.
+pub(crate) fn read_file(path: &Path) -> io::Result<Vec<String>> {
+    let contents = fs::read_to_string(path).map_err(|err| {
+        io::Error::new(
+            err.kind(),
+            format!("could not read glob file {}: {}", path.display(), err),
+        )
+    })?;
+
+    Ok(contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect())
+}
+
+fn expand_into(pattern: &str, result: &mut Vec<String>) {
+    let (open, close, commas) = match find_group(pattern) {
+        Some(group) => group,
+        None => {
+            if !result.iter().any(|p| p == pattern) {
+                result.push(pattern.to_string());
+            }
+            return;
+        }
+    };
+
+    let prefix = &pattern[..open];
+    let suffix = &pattern[close + 1..];
+    let mut start = open + 1;
+
+    for end in commas.into_iter().chain(iter::once(close)) {
+        expand_into(
+            &format!("{}{}{}", prefix, &pattern[start..end], suffix),
+            result,
+        );
+        start = end + 1;
+    }
+}
+
+/// Finds the first brace group containing at least one top-level comma.
+/// Returns the positions of the opening brace, the closing brace and the commas.
+fn find_group(pattern: &str) -> Option<(usize, usize, Vec<usize>)> {
+    let bytes = pattern.as_bytes();
+    let mut search_from = 0;
+
+    while let Some(open) = find_unescaped(bytes, search_from, b'{') {
+        let mut depth = 0;
+        let mut commas = Vec::new();
+        let mut index = open;
+
+        while index < bytes.len() {
+            match bytes[index] {
+                b'\\' => index += 1,
+                b'{' => depth += 1,
+                b'}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        if !commas.is_empty() {
+                            return Some((open, index, commas));
+                        }
+                        break;
+                    }
+                }
+                b',' if depth == 1 => commas.push(index),
+                _ => {}
+            }
+            index += 1;
+        }
+
+        search_from = open + 1;
+    }
+
+    None
+}
+
+fn find_unescaped(bytes: &[u8], from: usize, needle: u8) -> Option<usize> {
+    let mut index = 0;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'\\' => index += 1,
+            byte if byte == needle && index >= from => return Some(index),
+            _ => {}
+        }
+        index += 1;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{expand_braces, patterns};
+    use ignore::overrides::OverrideBuilder;
+    use std::path::Path;
+
+    /// Returns which of the given paths, relative to the root, a glob matches.
+    fn matches<'a>(glob: &str, paths: &[&'a str]) -> Vec<&'a str> {
+        let root = Path::new("/root");
+        let mut builder = OverrideBuilder::new(root);
+        for pattern in patterns(glob) {
+            builder.add(&pattern).unwrap();
+        }
+        let overrides = builder.build().unwrap();
+
+        paths
+            .iter()
+            .copied()
+            .filter(|path| overrides.matched(root.join(path), false).is_whitelist())
+            .collect()
+    }
+
+    const PATHS: &[&str] = &["main.rs", "src/main.rs", "a/src/main.rs"];
+
+    #[test]
+    fn anchors_globs_starting_with_a_slash() {
+        assert_eq!(matches("/src/*.rs", PATHS), ["src/main.rs"]);
+    }
+
+    #[test]
+    fn anchors_globs_starting_with_a_dot_slash() {
+        assert_eq!(patterns("./src/*.rs"), ["/src/*.rs"]);
+        assert_eq!(patterns("!./src/*.rs"), ["!/src/*.rs"]);
+        assert_eq!(matches("./src/*.rs", PATHS), ["src/main.rs"]);
+    }
+
+    #[test]
+    fn anchors_globs_containing_a_slash() {
+        assert_eq!(matches("src/*.rs", PATHS), ["src/main.rs"]);
+    }
+
+    #[test]
+    fn matches_nested_paths_with_double_stars() {
+        assert_eq!(
+            matches("**/src/*.rs", PATHS),
+            ["src/main.rs", "a/src/main.rs"]
+        );
+    }
+
+    #[test]
+    fn matches_globs_without_a_slash_at_any_depth() {
+        assert_eq!(matches("*.rs", PATHS), PATHS);
+    }
+
+    #[test]
+    fn expands_extensions() {
+        assert_eq!(expand_braces("*.{rs,toml}"), ["*.rs", "*.toml"]);
+    }
+
+    #[test]
+    fn expands_nested_groups() {
+        assert_eq!(
+            expand_braces("src/{a,b/{c,d}}.rs"),
+            ["src/a.rs", "src/b/c.rs", "src/b/d.rs"]
+        );
+    }
+
+    #[test]
+    fn keeps_escaped_characters() {
+        assert_eq!(expand_braces(r"\{a,b}"), [r"\{a,b}"]);
+        assert_eq!(expand_braces(r"*.{a\,b,c}"), [r"*.a\,b", "*.c"]);
+    }
+
+    #[test]
+    fn keeps_unbalanced_braces() {
+        assert_eq!(expand_braces("*.{rs,toml"), ["*.{rs,toml"]);
+        assert_eq!(expand_braces("*.rs}"), ["*.rs}"]);
+    }
+
+    #[test]
+    fn keeps_groups_without_alternatives() {
+        assert_eq!(expand_braces("*.{rs}"), ["*.{rs}"]);
+    }
+
+    #[test]
+    fn expands_negated_patterns() {
+        assert_eq!(expand_braces("!*.{a,b}"), ["!*.a", "!*.b"]);
+    }
+
+    #[test]
+    fn removes_duplicates() {
+        assert_eq!(expand_braces("*.{rs,rs}"), ["*.rs"]);
+    }
+}
@@ -0,0 +1,59 @@
+use std::io::{self, Write};
+
+/// Writes a string as a quoted JSON string.
+pub(crate) fn write_str(out: &mut impl Write, value: &str) -> io::Result<()> {
+    out.write_all(b"\"")?;
+
+    for c in value.chars() {
+        match c {
+            '"' => out.write_all(b"\\\"")?,
+            '\\' => out.write_all(b"\\\\")?,
+            '\n' => out.write_all(b"\\n")?,
+            '\r' => out.write_all(b"\\r")?,
+            '\t' => out.write_all(b"\\t")?,
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32)?,
+            c => write!(out, "{}", c)?,
+        }
+    }
+
+    out.write_all(b"\"")
+}
+
+/// Writes a list of strings as a JSON array.
+pub(crate) fn write_str_array<S: AsRef<str>>(out: &mut impl Write, values: &[S]) -> io::Result<()> {
+    out.write_all(b"[")?;
+
+    for (index, value) in values.iter().enumerate() {
+        if index != 0 {
+            out.write_all(b",")?;
+        }
+        write_str(out, value.as_ref())?;
+    }
+
+    out.write_all(b"]")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quoted(value: &str) -> String {
+        let mut out = Vec::new();
+        write_str(&mut out, value).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn writes_escaped_strings() {
+        assert_eq!(quoted("a\"b\\c"), r#""a\"b\\c""#);
+        assert_eq!(quoted("\n\r\t\u{1}"), r#""\n\r\t\u0001""#);
+        assert_eq!(quoted("café"), r#""café""#);
+    }
+
+    #[test]
+    fn writes_string_arrays() {
+        let mut out = Vec::new();
+        write_str_array(&mut out, &["a", "b\"c"]).unwrap();
+        assert_eq!(out, br#"["a","b\"c"]"#);
+    }
+}
@@ -0,0 +1,3127 @@
+use crate::eol::Eol;
+use crate::logging::LogLevel;
+use crate::paths::PathSeparator;
+use crate::printer::{HeaderStyle, Printer};
+use crate::progress::ProgressMode;
+use crate::template::StatFormat;
+use crate::theme::Theme;
+use clap::{Parser, Subcommand, ValueEnum};
+use ignore::overrides::{Override, OverrideBuilder};
+use ignore::WalkState::{Continue, Quit};
+use ignore::{DirEntry, WalkBuilder};
+use std::any::Any;
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, IsTerminal, Read, Seek, SeekFrom, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::{Duration, Instant};
+use std::{env, process, slice, thread};
+use termcolor::ColorChoice;
+
+mod argfile;
+mod buffer;
+mod checkpoint;
+mod convert;
+mod crc;
+mod csv;
+mod diff;
+mod editorconfig;
+mod encoding;
+mod eol;
+mod events;
+mod git;
+mod glob;
+mod json;
+mod logging;
+mod mounts;
+mod paths;
+mod plan;
+mod porcelain;
+mod printer;
+mod progress;
+mod report;
+mod stats;
+mod template;
+mod terminal;
+mod theme;
+mod zip;
+
+pub use crate::report::{FileOutcome, Report};
+
+type ErrorBox = Box<dyn std::error::Error + Send + Sync>;
+type Result<T> = std::result::Result<T, ErrorBox>;
+
+/// Environment variable which makes dry-run the default unless --write is given.
+const REQUIRE_WRITE_VAR: &str = "ADDEOL_REQUIRE_WRITE";
+
+/// Number of threads used by --io-bound.
+const IO_BOUND_THREADS: usize = 2;
+
+/// Exit code returned by --error-if-empty when no file matched.
+const EMPTY_EXIT_CODE: i32 = 3;
+
+/// How often to check for progress requests while no result comes in.
+const PROGRESS_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Parser, Debug, Clone)]
+#[clap(
+    author,
+    version,
+    about,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+struct Cli {
+    #[clap(subcommand)]
+    command: Option<Command>,
+
+    #[clap(flatten)]
+    args: Args,
+
+    /// Do not modify files (deprecated, use the check subcommand)
+    #[clap(short = 'n', long)]
+    dry_run: bool,
+
+    /// Modify files, when ADDEOL_REQUIRE_WRITE makes dry-run the default
+    #[clap(long, alias = "fix", conflicts_with_all = &["dry-run", "verify"])]
+    write: bool,
+
+    /// Do not modify files, and fail if any would be updated (recommended for CI)
+    ///
+    /// This is the same as the check subcommand.
+    #[clap(long)]
+    verify: bool,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum Command {
+    /// Report files which lack a final end-of-line, without modifying them
+    Check(Args),
+
+    /// Add the missing final end-of-line to files
+    Fix(Args),
+}
+
+#[derive(Parser, Debug, Clone)]
+struct Args {
+    /// Glob to match
+    ///
+    /// Globs follow the gitignore syntax, relative to the current directory: *.rs matches
+    /// at any depth, while a glob starting with / or ./, or holding a slash elsewhere than
+    /// at its end, is anchored. For instance /src/*.rs and src/*.rs only match in the src
+    /// directory of the current one, and **/src/*.rs matches in any src directory.
+    /// Anchored globs never match files outside of the current directory.
+    ///
+    /// A glob, a glob file, an extension or --only-extensionless is required, except
+    /// when reading from stdin, with --apply-plan and with --archive.
+    #[clap(short, long)]
+    glob: Vec<String>,
+
+    /// Only process files without an extension, such as Makefile or LICENSE
+    #[clap(long, conflicts_with = "ext")]
+    only_extensionless: bool,
+
+    /// Match all globs and extensions case-insensitively
+    #[clap(short = 'i', long)]
+    ignore_case: bool,
+
+    /// Read globs to match from a file, one per line
+    ///
+    /// Blank lines and lines starting with # are ignored.
+    #[clap(long, value_name = "FILE")]
+    glob_file: Vec<PathBuf>,
+
+    /// File extension to match, combined with the globs
+    #[clap(long, value_name = "EXT")]
+    ext: Vec<String>,
+
+    /// Path to search, or - to read from stdin and write to stdout
+    #[clap(default_value = ".")]
+    paths: Vec<String>,
+
+    /// Name of the file read from stdin, used to display it and to choose its end-of-line
+    #[clap(long, value_name = "PATH")]
+    stdin_filename: Option<PathBuf>,
+
+    /// What to write when stdin is empty
+    #[clap(long, value_enum, value_name = "POLICY", default_value = "passthrough")]
+    stdin_policy: StdinPolicy,
+
+    /// Display paths relative to this directory
+    #[clap(long, value_name = "DIR")]
+    root: Option<PathBuf>,
+
+    /// Resolve symlinks before displaying paths relative to the root
+    ///
+    /// Symlinked files whose target is outside of the root given with --root or
+    /// --relative-to-repo are then displayed with the absolute path of their target.
+    #[clap(long)]
+    symlink_safe_paths: bool,
+
+    /// Separator to use in displayed paths
+    #[clap(long, value_enum, value_name = "SEPARATOR", default_value = "native")]
+    path_separator: PathSeparator,
+
+    /// Print the summary on a single line
+    #[clap(long)]
+    oneline_summary: bool,
+
+    /// Template of the summary line, which implies --oneline-summary
+    ///
+    /// Placeholders are {total}, {updated}, {errors}, {symlinks} (skipped symlinks),
+    /// {elapsed} and {action} ("updated" or "to update"), and {{ and }} stand for literal
+    /// braces. The default is:
+    /// "{updated} {action}, {total} total, {errors} errors ({elapsed})".
+    #[clap(long, value_name = "TEMPLATE", value_parser = StatFormat::parse)]
+    stat_format: Option<StatFormat>,
+
+    /// Style of the labels preceding file paths
+    #[clap(long, value_enum, value_name = "STYLE", default_value = "words")]
+    header_style: HeaderStyle,
+
+    /// Color palette of the output
+    ///
+    /// The mono theme disables colors, while keeping the header style.
+    #[clap(long, value_enum, default_value = "auto")]
+    theme: Theme,
+
+    /// Shorten long paths in the middle to fit the given width
+    ///
+    /// The width defaults to the terminal width. Paths are never shortened in
+    /// machine-readable output.
+    #[clap(long, value_name = "WIDTH", min_values = 0, require_equals = true)]
+    truncate_paths: Option<Option<usize>>,
+
+    /// Display paths relative to the root of the enclosing git repository
+    ///
+    /// Absolute paths are displayed when not inside a git repository.
+    #[clap(long, conflicts_with = "root")]
+    relative_to_repo: bool,
+
+    /// Display canonical paths, with symlinks and .. components resolved
+    ///
+    /// Unlike the absolute paths displayed outside of a repository with
+    /// --relative-to-repo, this shows the same path for a file reached from different
+    /// places. Paths which can't be resolved are displayed as they are.
+    #[clap(long, conflicts_with_all = &["root", "relative-to-repo", "symlink-safe-paths"])]
+    canonical: bool,
+
+    /// Only process the files changed since the given git reference
+    #[clap(long, value_name = "REF")]
+    since: Option<String>,
+
+    /// Only process the files tracked by git, under the search paths
+    #[clap(long, conflicts_with = "since")]
+    tracked: bool,
+
+    /// Only process the files given as arguments, and the files directly in the given directories
+    ///
+    /// This limits the walk to a depth of 1: paths given as arguments are at depth 0,
+    /// and the entries directly inside a directory argument are at depth 1.
+    #[clap(long)]
+    no_recursive: bool,
+
+    /// Keep symlinked directories to search as given, instead of replacing them with their target
+    ///
+    /// Resolved directories are walked from their target, so the files found in them are
+    /// displayed with the absolute path of the target. Symlinked files are always kept as
+    /// given, and so are all paths with --no-dereference.
+    #[clap(long)]
+    no_resolve_roots: bool,
+
+    /// Don't descend into directories on network file systems, such as NFS or SMB
+    ///
+    /// The pruned directories are logged at the info level. This has no effect where
+    /// the file system type can't be determined.
+    #[clap(long)]
+    skip_remote: bool,
+
+    /// Don't read ignore files
+    #[clap(long)]
+    no_ignore: bool,
+
+    /// Don't read ignore files from the parent directories of the paths to search
+    #[clap(long)]
+    no_parent_ignore: bool,
+
+    /// Include hidden files
+    #[clap(long)]
+    hidden: bool,
+
+    /// Include hidden files, but don't descend into hidden directories such as .git
+    #[clap(long, conflicts_with = "hidden")]
+    hidden_files: bool,
+
+    /// List all included files
+    #[clap(long)]
+    list: bool,
+
+    /// Only list the files which lack a final end-of-line, and errors
+    #[clap(long, conflicts_with_all = &["list", "show-skipped"])]
+    list_missing: bool,
+
+    /// Only print errors, followed by their count if any
+    ///
+    /// Nothing is printed when all goes well, which suits scheduled jobs. The exit code
+    /// still reflects the errors, unless --ignore-errors is given.
+    #[clap(
+        long,
+        conflicts_with_all = &["list", "list-missing", "show-skipped", "oneline-summary", "stat-format"]
+    )]
+    errors_only: bool,
+
+    /// Exit successfully even when some files could not be processed
+    #[clap(long, overrides_with_all = &["keep-going", "fail-fast"])]
+    ignore_errors: bool,
+
+    /// Process all files even after an error, and exit with an error code (default)
+    #[clap(long, overrides_with_all = &["ignore-errors", "fail-fast"])]
+    keep_going: bool,
+
+    /// Stop at the first file which could not be processed
+    #[clap(long, overrides_with_all = &["ignore-errors", "keep-going"])]
+    fail_fast: bool,
+
+    /// Convert all line endings to LF, skipping binary files
+    ///
+    /// The whole file is rewritten, and files containing NUL bytes are considered
+    /// binary. The --eol option doesn't apply.
+    #[clap(long)]
+    to_lf: bool,
+
+    /// Convert all line endings to CRLF, skipping binary files
+    ///
+    /// The whole file is rewritten, and files containing NUL bytes are considered
+    /// binary. The --eol option doesn't apply.
+    #[clap(long, conflicts_with = "to-lf")]
+    to_crlf: bool,
+
+    /// Replace a Unicode line or paragraph separator ending a file with an end-of-line
+    ///
+    /// Files ending with U+2028 or U+2029 are considered up to date otherwise.
+    #[clap(long)]
+    replace_unicode_separator: bool,
+
+    /// End files with exactly one end-of-line, removing trailing whitespace and blank lines
+    ///
+    /// Files holding nothing but spaces and tabs are emptied, while files holding only
+    /// blank lines are reduced to a single end-of-line.
+    #[clap(long, conflicts_with_all = &["to-lf", "to-crlf", "strip-trailing-whitespace"])]
+    clean_eof: bool,
+
+    /// Apply the usual end-of-file cleanups together, skipping binary files
+    ///
+    /// This ensures files end with an end-of-line, collapses trailing blank lines
+    /// into a single end-of-line, and removes trailing spaces and tabs from the last
+    /// line, as --clean-eof does. Files containing NUL bytes are considered binary
+    /// and left untouched.
+    #[clap(
+        long,
+        conflicts_with_all = &["to-lf", "to-crlf", "strip-trailing-whitespace", "append-sequence"]
+    )]
+    fix_all: bool,
+
+    /// Remove trailing spaces and tabs from every line, skipping binary files
+    ///
+    /// The whole file is rewritten, and files containing NUL bytes are considered
+    /// binary. Line endings are kept as they are.
+    #[clap(long)]
+    strip_trailing_whitespace: bool,
+
+    /// Add an end-of-line to empty files too
+    #[clap(long)]
+    newline_empty: bool,
+
+    /// Print nothing when no file matched
+    #[clap(long)]
+    no_summary_on_empty: bool,
+
+    /// List files which were skipped, and why
+    #[clap(long)]
+    show_skipped: bool,
+
+    /// Fail if one of the globs didn't match any file
+    ///
+    /// This catches typos in the globs, which would otherwise silently skip files.
+    #[clap(long)]
+    require_glob_match: bool,
+
+    /// Count the errors by message in the summary, instead of listing each of them
+    #[clap(long)]
+    summarize_errors: bool,
+
+    /// Skip files larger than this size (accepts K, M and G suffixes)
+    #[clap(long, value_name = "SIZE", value_parser = parse_size)]
+    max_filesize: Option<u64>,
+
+    /// Skip files smaller than this size (accepts K, M and G suffixes)
+    #[clap(long, value_name = "SIZE", value_parser = parse_size)]
+    min_filesize: Option<u64>,
+
+    /// Number of threads to use (0 picks a number based on the available CPUs)
+    #[clap(short = 'j', long, value_name = "N")]
+    threads: Option<usize>,
+
+    /// Use few threads, to avoid thrashing slow disks such as spinning hard drives
+    ///
+    /// Scans on such disks are limited by seeks rather than CPU, and parallel accesses
+    /// make them slower. This uses 2 threads, unless --threads is given.
+    #[clap(long)]
+    io_bound: bool,
+
+    /// Stop searching for files once this many matching files were found
+    ///
+    /// The files count as soon as they're found, whether they're then updated, up to
+    /// date, skipped or failed, which makes this suited to sampling a large tree.
+    #[clap(long, value_name = "N")]
+    max_matches: Option<usize>,
+
+    /// Process files one at a time, in a stable order
+    ///
+    /// Directory entries are sorted by name as they're read, so results are streamed
+    /// in a reproducible order without being buffered.
+    #[clap(long, visible_alias = "sort-stream")]
+    no_parallel: bool,
+
+    /// Print diagnostics about the walk and the processing of files to stderr
+    #[clap(long, value_enum, value_name = "LEVEL", default_value = "off")]
+    log_level: LogLevel,
+
+    /// Time a few scans without changing any file, and print the results to stderr
+    #[clap(
+        long,
+        value_name = "RUNS",
+        min_values = 0,
+        require_equals = true,
+        default_missing_value = "5",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    benchmark: Option<u64>,
+
+    /// Show a progress line on stderr while processing
+    ///
+    /// The precount mode walks the paths a first time to count the files without
+    /// opening them, to show a percentage and an estimated time left.
+    #[clap(
+        long,
+        value_enum,
+        value_name = "MODE",
+        min_values = 0,
+        require_equals = true,
+        default_missing_value = "spinner"
+    )]
+    progress: Option<ProgressMode>,
+
+    /// Warn about files whose last line is longer than this number of bytes
+    #[clap(long, value_name = "N")]
+    warn_long_final_line: Option<u64>,
+
+    /// Fail with exit code 3 when no file matched, to catch mistyped globs
+    #[clap(long)]
+    error_if_empty: bool,
+
+    /// Exit code returned by check and --verify when some files need to be updated
+    #[clap(long, value_name = "N", default_value_t = 1)]
+    exit_code_on_change: i32,
+
+    /// Allow processing the root of a file system, such as /
+    #[clap(long)]
+    force: bool,
+
+    /// Ask for confirmation before updating more than this number of files
+    ///
+    /// Files are checked in a first pass, and nothing is changed if the confirmation is
+    /// refused. Without a terminal to ask, the run is aborted unless --yes is given.
+    #[clap(long, value_name = "N")]
+    confirm_over: Option<usize>,
+
+    /// Don't ask for confirmation, even with --confirm-over
+    #[clap(long, short = 'y')]
+    yes: bool,
+
+    /// Write changes to a temporary file and rename it over the original
+    #[clap(long)]
+    atomic: bool,
+
+    /// Print updated files to stdout separated by NUL characters, and the report to stderr
+    #[clap(long)]
+    print0: bool,
+
+    /// Flush stdout at least this often during the search, in milliseconds (0 to disable)
+    ///
+    /// Results are written line by line, but output without line breaks such as --print0
+    /// is otherwise held until the buffer fills up, which looks stalled through a pipe.
+    #[clap(long, value_name = "MS", default_value = "1000")]
+    flush_interval: u64,
+
+    /// When to use colors
+    ///
+    /// The --no-color and --force-color shorthands take precedence over this option,
+    /// and --no-color wins if both are given. The NO_COLOR and TERM=dumb environment
+    /// variables are only taken into account in auto mode.
+    #[clap(long, value_enum, value_name = "WHEN", default_value = "auto")]
+    color: ColorMode,
+
+    /// Disable colors, regardless of --color
+    #[clap(long)]
+    no_color: bool,
+
+    /// Always use colors, even when not writing to a terminal
+    #[clap(long)]
+    force_color: bool,
+
+    /// Separator between groups of thousands in counts (empty to disable)
+    #[clap(long, value_name = "SEP", default_value = ",")]
+    thousands_separator: String,
+
+    /// Output format
+    ///
+    /// The diff format prints a unified diff of the changes to stdout, and the
+    /// report to stderr.
+    ///
+    /// The events format prints a `start` event, a `file` event per result, and an
+    /// `end` event, as JSON objects one per line on stdout, and the report to stderr.
+    #[clap(long, value_enum, default_value = "text")]
+    format: Format,
+
+    /// Print one `<status> <path>` line per file in a stable format for scripts
+    ///
+    /// Paths containing whitespace, quotes or control characters are quoted and
+    /// escaped C-style. No summary is printed.
+    #[clap(long, conflicts_with_all = &["print0", "format"])]
+    porcelain: bool,
+
+    /// Sync written files to disk before moving on
+    ///
+    /// This guarantees the changes survive a crash or power loss, at the cost of
+    /// waiting for the storage device on every updated file.
+    #[clap(long, conflicts_with = "no-sync")]
+    sync: bool,
+
+    /// Do not flush written files
+    ///
+    /// This is the fastest option, but leaves it entirely up to the OS to decide
+    /// when the changes reach the disk.
+    #[clap(long)]
+    no_sync: bool,
+
+    /// Read the end of each file again after writing it, and report an error if it
+    /// doesn't match what was written
+    ///
+    /// This catches file systems which silently fail writes.
+    #[clap(long)]
+    verify_writes: bool,
+
+    /// Write the changes which would be made to this file, without modifying anything
+    ///
+    /// The plan lists the bytes which would replace the end of each file in hex, one file
+    /// per line, and can be reviewed before being applied with --apply-plan.
+    #[clap(long, value_name = "FILE", conflicts_with = "apply-plan")]
+    plan: Option<PathBuf>,
+
+    /// Make exactly the changes listed in a plan written by --plan
+    ///
+    /// Files whose size, or the part the plan replaces along with the few kilobytes
+    /// before it, changed since the plan was made are reported as diverged and left
+    /// untouched, as are files which gained a final end-of-line. The paths to search and the globs
+    /// don't apply.
+    #[clap(long, value_name = "FILE")]
+    apply_plan: Option<PathBuf>,
+
+    /// Experimental: add the missing final end-of-line to the text entries of a zip archive
+    ///
+    /// The archive is rewritten as a whole. Only entries stored without compression can
+    /// be modified: compressed and encrypted entries are skipped, as are binary ones. The
+    /// paths to search and the globs don't apply.
+    #[clap(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = &["apply-plan", "plan", "clean-eof", "fix-all"]
+    )]
+    archive: Option<PathBuf>,
+
+    /// Append the paths of the processed files to this file as they complete
+    ///
+    /// Nothing is recorded in dry-run mode. Files which couldn't be processed are not
+    /// recorded either, so that they're retried when resuming.
+    #[clap(long, value_name = "FILE")]
+    checkpoint: Option<PathBuf>,
+
+    /// Skip the files recorded in a checkpoint written by an interrupted run
+    ///
+    /// The paths are compared as walked, starting with the paths to search as given,
+    /// rather than as displayed. The run must then be resumed from the same directory
+    /// with the same paths to search. The same file can be given to --checkpoint to keep
+    /// recording.
+    #[clap(long, value_name = "FILE")]
+    resume: Option<PathBuf>,
+
+    /// Number of bytes read at once when scanning the end of files backwards
+    ///
+    /// Larger chunks mean fewer reads on files ending with a lot of whitespace.
+    #[clap(
+        long,
+        value_name = "BYTES",
+        default_value = "4096",
+        value_parser = clap::value_parser!(u64).range(1..=buffer::MAX_TAIL_CHUNK as u64)
+    )]
+    tail_chunk: u64,
+
+    /// Write the final counts as JSON to this file
+    #[clap(long, value_name = "FILE")]
+    stats_json: Option<PathBuf>,
+
+    /// Write the paths of the files which are already up to date to this file, one per line
+    #[clap(long, value_name = "FILE")]
+    report_unchanged: Option<PathBuf>,
+
+    /// End-of-line to add
+    ///
+    /// A file which ends with a lone carriage return is completed to the chosen
+    /// end-of-line: a line feed is appended for CRLF, and the carriage return is
+    /// replaced for LF. In auto mode, a file without any other end-of-line to
+    /// compare with is considered to be terminated by its carriage return.
+    ///
+    /// When auto mode can't detect anything, such as in single-line files, the
+    /// end-of-line git would use in the working tree is chosen, from the file's `eol`
+    /// attribute, or the `core.autocrlf` and `core.eol` settings. Then comes the
+    /// `end_of_line` property of .editorconfig files, and --fallback-eol otherwise.
+    #[clap(long, value_enum, default_value = "native")]
+    eol: Eol,
+
+    /// End-of-line used when auto mode can't detect nor find a configured one
+    #[clap(long, value_enum, value_name = "EOL", default_value = "native")]
+    fallback_eol: eol::Fallback,
+
+    /// Exact bytes to end files with, in hexadecimal (e.g. 0A or 0D0A)
+    ///
+    /// Files which already end with this sequence are left untouched, and the
+    /// sequence is appended to the others as is. This overrides --eol. The changes are
+    /// omitted from the diff format.
+    #[clap(
+        long,
+        value_name = "HEX",
+        value_parser = eol::parse_sequence,
+        conflicts_with_all = &["eol", "to-lf", "to-crlf", "strip-trailing-whitespace", "clean-eof"]
+    )]
+    append_sequence: Option<&'static [u8]>,
+
+    /// Modify the target of symlinked files (default)
+    ///
+    /// The symlinks themselves are kept, including with --atomic. Symlinked directories
+    /// are not followed.
+    #[clap(long, overrides_with = "no-dereference")]
+    dereference: bool,
+
+    /// Skip symlinked files instead of modifying their target
+    #[clap(long, overrides_with = "dereference")]
+    no_dereference: bool,
+
+    #[clap(skip)]
+    dry_run: bool,
+
+    #[clap(skip)]
+    check: bool,
+
+    /// Files already processed according to the checkpoint given to --resume
+    #[clap(skip)]
+    resumed: HashSet<PathBuf>,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum ColorMode {
+    /// Use colors when writing to a terminal
+    Auto,
+    /// Always use colors
+    Always,
+    /// Never use colors
+    Never,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum StdinPolicy {
+    /// Write nothing, as for empty files (unless --newline-empty is given)
+    Passthrough,
+    /// Always write an end-of-line
+    Ensure,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    /// Human-readable report
+    Text,
+    /// Unified diff of the changes
+    Diff,
+    /// Patch which makes the changes when given to git apply, implies --dry-run
+    ///
+    /// Binary and non UTF-8 files, and whole file rewrites, are left out.
+    GitPatch,
+    /// Stream of JSON events, one per line
+    Events,
+    /// Comma-separated values, one row per file
+    Csv,
+    /// Tab-separated values, one row per file
+    Tsv,
+}
+
+impl Format {
+    /// Returns the field delimiter of tabular formats.
+    fn delimiter(self) -> Option<u8> {
+        match self {
+            Format::Csv => Some(b','),
+            Format::Tsv => Some(b'\t'),
+            _ => None,
+        }
+    }
+
+    fn is_diff(self) -> bool {
+        matches!(self, Format::Diff | Format::GitPatch)
+    }
+
+    /// Returns whether the encoding and end-of-line of files are reported, including
+    /// for the files which are up to date.
+    fn reports_detection(self) -> bool {
+        matches!(self, Format::Events | Format::Csv | Format::Tsv)
+    }
+}
+
+impl Args {
+    fn color_choice(&self) -> ColorChoice {
+        if self.no_color {
+            return ColorChoice::Never;
+        }
+
+        if self.force_color {
+            return ColorChoice::Always;
+        }
+
+        match self.color {
+            ColorMode::Auto => ColorChoice::Auto,
+            ColorMode::Always => ColorChoice::Always,
+            ColorMode::Never => ColorChoice::Never,
+        }
+    }
+
+    /// Returns whether whole files are rewritten, instead of only their end.
+    fn rewrites_files(&self) -> bool {
+        self.convert_to().is_some() || self.strip_trailing_whitespace
+    }
+
+    /// Returns whether the trailing whitespace and blank lines are replaced by a single end-of-line.
+    fn cleans_eof(&self) -> bool {
+        self.clean_eof || self.fix_all
+    }
+
+    fn has_globs(&self) -> bool {
+        !self.glob.is_empty() || !self.glob_file.is_empty()
+    }
+
+    /// Returns the end-of-line all line endings are converted to, if any.
+    fn convert_to(&self) -> Option<&'static [u8]> {
+        if self.to_lf {
+            Some(eol::LF)
+        } else if self.to_crlf {
+            Some(eol::CRLF)
+        } else {
+            None
+        }
+    }
+}
+
+impl Cli {
+    fn into_args(self) -> Args {
+        let args = match self.command {
+            Some(Command::Check(args)) => Args {
+                dry_run: true,
+                check: true,
+                ..args
+            },
+            Some(Command::Fix(args)) => args,
+            None => Args {
+                dry_run: self.dry_run
+                    || self.verify
+                    || (!self.write && env::var_os(REQUIRE_WRITE_VAR).is_some_and(|v| v != "0")),
+                check: self.verify,
+                ..self.args
+            },
+        };
+
+        Args {
+            dry_run: args.dry_run || args.plan.is_some() || args.format == Format::GitPatch,
+            ..args
+        }
+    }
+}
+
+/// A change made to a file, or which would be made in dry-run mode.
+struct Change {
+    /// Number of bytes removed from the file
+    removed: u64,
+    /// Number of bytes added to the file
+    added: u64,
+    /// End-of-line the file ends with after the change
+    eol: &'static [u8],
+    /// Encoding detected from the byte order mark
+    encoding: &'static str,
+    /// Last line of the file, only read when needed for the output
+    last_line: Option<LastLine>,
+    /// Edit which would be made, only kept when writing a plan
+    planned: Option<plan::PlannedEdit>,
+}
+
+/// Encoding and final end-of-line of a file, as reported by the machine-readable formats.
+#[derive(Clone, Copy)]
+struct Detected {
+    encoding: &'static str,
+    /// End-of-line the file ends with, if any
+    eol: Option<&'static [u8]>,
+}
+
+struct LastLine {
+    /// One-based line number
+    number: u64,
+    before: Vec<u8>,
+    after: Vec<u8>,
+}
+
+/// Outcome of processing a single file.
+enum Outcome {
+    /// The file is left untouched, with what was detected in it when it is reported
+    UpToDate(Option<Detected>),
+    Updated(Change),
+    Binary,
+}
+
+enum FileResult {
+    UpdatedFile(DirEntry, Change),
+    UpdatedInPlace(DirEntry, Change),
+    UpToDateFile(DirEntry, Option<Detected>),
+    SkippedTooLarge(DirEntry),
+    SkippedTooSmall(DirEntry),
+    SkippedSymlink(DirEntry),
+    SkippedBinary(DirEntry),
+    /// File already processed by the run being resumed
+    SkippedResumed(DirEntry),
+    /// Warning about a file whose last line is longer than the limit, with its length
+    LongFinalLine(PathBuf, u64),
+    FileError(DirEntry, ErrorBox),
+    /// Processing the file panicked, which is caught so that the other files are processed
+    Panicked(DirEntry, ErrorBox),
+    UnreadableDirectory(PathBuf, ErrorBox),
+    /// Error reported by the walker, with the path it concerns when known
+    WalkError(Option<PathBuf>, ErrorBox),
+}
+
+impl FileResult {
+    /// Short name of the outcome, used in the machine-readable formats
+    fn status(&self) -> &'static str {
+        match self {
+            FileResult::UpdatedFile(_, _) => "updated",
+            FileResult::UpdatedInPlace(_, _) => "in-place",
+            FileResult::UpToDateFile(_, _) => "up-to-date",
+            FileResult::SkippedTooLarge(_) => "too-large",
+            FileResult::SkippedTooSmall(_) => "too-small",
+            FileResult::SkippedSymlink(_) => "symlink",
+            FileResult::SkippedBinary(_) => "binary",
+            FileResult::SkippedResumed(_) => "resumed",
+            FileResult::LongFinalLine(_, _) => "long-line",
+            FileResult::FileError(_, _) | FileResult::WalkError(_, _) => "error",
+            FileResult::Panicked(_, _) => "panic",
+            FileResult::UnreadableDirectory(_, _) => "unreadable",
+        }
+    }
+
+    fn path(&self) -> Option<&Path> {
+        match self {
+            FileResult::UpdatedFile(entry, _)
+            | FileResult::UpdatedInPlace(entry, _)
+            | FileResult::UpToDateFile(entry, _)
+            | FileResult::SkippedTooLarge(entry)
+            | FileResult::SkippedTooSmall(entry)
+            | FileResult::SkippedSymlink(entry)
+            | FileResult::SkippedBinary(entry)
+            | FileResult::SkippedResumed(entry)
+            | FileResult::FileError(entry, _)
+            | FileResult::Panicked(entry, _) => Some(entry.path()),
+            FileResult::UnreadableDirectory(path, _) | FileResult::LongFinalLine(path, _) => {
+                Some(path)
+            }
+            FileResult::WalkError(path, _) => path.as_deref(),
+        }
+    }
+
+    fn change(&self) -> Option<&Change> {
+        match self {
+            FileResult::UpdatedFile(_, change) | FileResult::UpdatedInPlace(_, change) => {
+                Some(change)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the encoding and end-of-line of the file, after the change if there is one.
+    fn detected(&self) -> Option<Detected> {
+        match self {
+            FileResult::UpToDateFile(_, detected) => *detected,
+            _ => self.change().map(|change| Detected {
+                encoding: change.encoding,
+                eol: Some(change.eol),
+            }),
+        }
+    }
+
+    fn error(&self) -> Option<&ErrorBox> {
+        match self {
+            FileResult::FileError(_, err)
+            | FileResult::Panicked(_, err)
+            | FileResult::UnreadableDirectory(_, err)
+            | FileResult::WalkError(_, err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Options of a run, as given on the command line.
+#[derive(Debug, Clone)]
+pub struct Options(Args);
+
+impl Options {
+    /// Parses the options from command-line arguments, without the program name.
+    pub fn parse<I, T>(args: I) -> std::result::Result<Options, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<std::ffi::OsString> + Clone,
+    {
+        let args = std::iter::once("addeol".into()).chain(args.into_iter().map(Into::into));
+        Ok(Options(Cli::try_parse_from(args)?.into_args()))
+    }
+}
+
+/// Processes the files selected by the options without printing anything, and returns
+/// the aggregate counts along with the outcome of each file.
+///
+/// Reading from stdin, plans and archives are only supported by the command line.
+pub fn run(options: &Options) -> Result<Report> {
+    let args = &options.0;
+
+    if args.apply_plan.is_some()
+        || args.plan.is_some()
+        || args.archive.is_some()
+        || args.paths.iter().any(|path| path == "-")
+    {
+        return Err("stdin, plans and archives are only supported by the command line".into());
+    }
+
+    require_selection(args)?;
+
+    let mut outcomes = Vec::new();
+    let mut report = walk_files(args, build_walker(args)?, |result, _| {
+        outcomes.push(FileOutcome::new(result));
+        Ok(())
+    })?;
+
+    report.outcomes = outcomes;
+    Ok(report)
+}
+
+fn require_selection(args: &Args) -> Result<()> {
+    if !args.has_globs() && args.ext.is_empty() && !args.only_extensionless {
+        return Err("one of --glob, --glob-file, --ext or --only-extensionless is required".into());
+    }
+    Ok(())
+}
+
+/// Entry point of the command line, which exits with the appropriate code.
+pub fn main() {
+    let args = match argfile::expand_args(env::args_os()) {
+        Ok(args) => Cli::parse_from(args).into_args(),
+        Err(msg) => {
+            eprintln!("{}", msg);
+            process::exit(1);
+        }
+    };
+
+    match run_cli(&args) {
+        Ok(report) => {
+            if args.error_if_empty && report.file_count == 0 {
+                eprintln!("no file matched the given globs and paths");
+                process::exit(EMPTY_EXIT_CODE);
+            }
+
+            if report.error_count != 0 && !args.ignore_errors {
+                process::exit(1);
+            }
+
+            if args.check && report.updated_count != 0 {
+                process::exit(args.exit_code_on_change);
+            }
+        }
+        Err(msg) => {
+            eprintln!("{}", msg);
+            process::exit(1);
+        }
+    }
+}
+
+/// Runs the command and prints its results.
+fn run_cli(args: &Args) -> Result<Report> {
+    logging::init(args.log_level);
+
+    if let Some(ref path) = args.apply_plan {
+        return run_apply_plan(path, args);
+    }
+
+    if let Some(ref path) = args.archive {
+        return run_archive(path, args);
+    }
+
+    if args.paths.iter().any(|path| path == "-") {
+        return run_stdin(args);
+    }
+
+    require_selection(args)?;
+
+    let resumed_args;
+    let args = match args.resume {
+        Some(ref path) => {
+            resumed_args = Args {
+                resumed: checkpoint::read(path)?,
+                ..args.clone()
+            };
+            &resumed_args
+        }
+        None => args,
+    };
+
+    let walker = build_walker(args)?;
+
+    progress::install_handler();
+
+    let root = if args.relative_to_repo {
+        git::toplevel().ok()
+    } else {
+        args.root.as_deref().map(paths::absolute).transpose()?
+    };
+
+    let mut printer =
+        if args.print0 || args.porcelain || args.format != Format::Text || args.benchmark.is_some()
+        {
+            Printer::stderr(args.color_choice())
+        } else {
+            Printer::new(args.color_choice())
+        }
+        .with_absolute_paths(args.relative_to_repo && root.is_none())
+        .with_root(root)
+        .with_resolved_paths(args.symlink_safe_paths)
+        .with_canonical_paths(args.canonical)
+        .with_header_style(args.header_style)
+        .with_theme(args.theme)
+        .with_path_separator(args.path_separator)
+        .with_max_path_width(
+            args.truncate_paths
+                .map(|width| width.unwrap_or_else(terminal::width)),
+        )
+        .with_thousands_separator(args.thousands_separator.clone());
+
+    if let Some(runs) = args.benchmark {
+        return benchmark(args, walker, runs, &mut printer);
+    }
+
+    let walker = match args.confirm_over {
+        Some(limit) if !args.dry_run && !args.yes => {
+            confirm_changes(args, walker, limit)?;
+            build_walker(args)?
+        }
+        _ => walker,
+    };
+
+    if args.format == Format::Events {
+        events::write_start(&mut io::stdout(), args, &read_globs(args)?)?;
+    }
+
+    if let Some(delimiter) = args.format.delimiter() {
+        csv::write_header(&mut io::stdout(), delimiter)?;
+    }
+
+    let mut unchanged = match args.report_unchanged {
+        Some(ref path) => {
+            Some(io::BufWriter::new(File::create(path).map_err(|err| {
+                format!("could not create {}: {}", path.display(), err)
+            })?))
+        }
+        None => None,
+    };
+
+    let mut bar = args.progress.map(|mode| {
+        let total = match (mode, &walker) {
+            (ProgressMode::Precount, Some(walker)) => Some(count_files(args, walker)),
+            (ProgressMode::Precount, None) => Some(0),
+            (ProgressMode::Spinner, _) => None,
+        };
+        progress::Bar::new(total)
+    });
+
+    let mut plan = match args.plan {
+        Some(ref path) => Some(
+            plan::PlanWriter::create(path)
+                .map_err(|err| format!("could not create {}: {}", path.display(), err))?,
+        ),
+        None => None,
+    };
+
+    let mut checkpoint = match args.checkpoint {
+        Some(ref path) => Some(
+            checkpoint::Checkpoint::open(path)
+                .map_err(|err| format!("could not open checkpoint {}: {}", path.display(), err))?,
+        ),
+        None => None,
+    };
+
+    let mut glob_tracker = if args.require_glob_match {
+        Some(glob::MatchTracker::new(
+            &read_globs(args)?,
+            &paths::current_dir()?,
+            args.ignore_case,
+        )?)
+    } else {
+        None
+    };
+
+    let report = walk_files(args, walker, |result, duration| {
+        if let (Some(tracker), Some(path)) = (&mut glob_tracker, result.path()) {
+            tracker.record(path);
+        }
+
+        if let (Some(checkpoint), Some(path)) = (&mut checkpoint, result.path()) {
+            let processed = result.error().is_none()
+                && !matches!(
+                    result,
+                    FileResult::LongFinalLine(..) | FileResult::SkippedResumed(_)
+                );
+            if processed && !args.dry_run {
+                checkpoint.record(path)?;
+            }
+        }
+
+        if let (Some(out), FileResult::UpToDateFile(entry, _)) = (&mut unchanged, result) {
+            writeln!(out, "{}", args.path_separator.apply(entry.path()).display())?;
+        }
+
+        if let (Some(plan), Some(change)) = (&mut plan, result.change()) {
+            if let Some(ref planned) = change.planned {
+                plan.write(planned)?;
+            }
+        }
+
+        if let Some(ref mut bar) = bar {
+            bar.clear();
+            print_result(&mut printer, result, duration, args)?;
+            if !matches!(result, FileResult::LongFinalLine(..)) {
+                bar.tick();
+            }
+            return Ok(());
+        }
+
+        print_result(&mut printer, result, duration, args)
+    })?;
+
+    if let Some(ref mut bar) = bar {
+        bar.clear();
+    }
+
+    if let Some(mut out) = unchanged {
+        out.flush()?;
+    }
+
+    if let Some(plan) = plan {
+        plan.finish()?;
+    }
+
+    let skip_summary = args.porcelain || (args.no_summary_on_empty && report.file_count == 0);
+    if !skip_summary {
+        if args.errors_only {
+            print_error_summary(&mut printer, &report, args)?;
+        } else if args.oneline_summary || args.stat_format.is_some() {
+            print_oneline_summary(&mut printer, &report, args)?;
+        } else {
+            print_summary(&mut printer, &report, args)?;
+        }
+    }
+
+    if args.format == Format::Events {
+        events::write_end(&mut io::stdout(), &report)?;
+    }
+
+    if let Some(ref path) = args.stats_json {
+        stats::write_json(path, &report)?;
+    }
+
+    if let Some(tracker) = glob_tracker {
+        let unmatched = tracker.unmatched().collect::<Vec<_>>();
+        if !unmatched.is_empty() {
+            return Err(format!("some globs matched no file: {}", unmatched.join(", ")).into());
+        }
+    }
+
+    Ok(report)
+}
+
+/// Processes the content of stdin, and writes it to stdout unless in dry-run mode.
+fn run_stdin(args: &Args) -> Result<Report> {
+    if args.paths.len() != 1 {
+        return Err("- can't be combined with other paths".into());
+    }
+
+    let start = Instant::now();
+    let path = args
+        .stdin_filename
+        .clone()
+        .unwrap_or_else(|| PathBuf::from("<stdin>"));
+
+    let mut printer = Printer::stderr(args.color_choice())
+        .with_theme(args.theme)
+        .with_thousands_separator(args.thousands_separator.clone());
+
+    let mut content = Vec::new();
+    io::stdin().read_to_end(&mut content)?;
+
+    let args = &Args {
+        newline_empty: args.newline_empty || args.stdin_policy == StdinPolicy::Ensure,
+        ..args.clone()
+    };
+
+    let mut report = Report::default();
+
+    match process_stdin(&mut content, &path, args)? {
+        Outcome::Updated(change) => {
+            report.record_update(&change);
+
+            if args.dry_run {
+                printer.write_updated_path(&path, true)?;
+            }
+        }
+        Outcome::UpToDate(_) => report.file_count += 1,
+        Outcome::Binary => report.binary_count += 1,
+    }
+
+    if !args.dry_run {
+        io::stdout().write_all(&content)?;
+        io::stdout().flush()?;
+    }
+
+    report.elapsed = start.elapsed();
+    Ok(report)
+}
+
+/// Applies the change needed by the content read from stdin, if any.
+fn process_stdin(content: &mut Vec<u8>, path: &Path, args: &Args) -> Result<Outcome> {
+    if (args.rewrites_files() || args.fix_all) && content.contains(&0) {
+        return Ok(Outcome::Binary);
+    }
+
+    match plan_content(content, path, args)? {
+        Some((edit, change)) => {
+            edit.apply_to(content);
+            Ok(Outcome::Updated(change))
+        }
+        None => Ok(Outcome::UpToDate(None)),
+    }
+}
+
+/// Makes the changes listed in a plan, unless in dry-run mode.
+fn run_apply_plan(path: &Path, args: &Args) -> Result<Report> {
+    let start = Instant::now();
+    let planned = plan::read(path)?;
+
+    let mut printer = Printer::new(args.color_choice())
+        .with_header_style(args.header_style)
+        .with_theme(args.theme)
+        .with_path_separator(args.path_separator)
+        .with_thousands_separator(args.thousands_separator.clone());
+
+    let mut report = Report::default();
+
+    for planned in planned {
+        match apply_planned(&planned, args) {
+            Ok(false) => {
+                report.file_count += 1;
+                report.diverged_count += 1;
+                printer.write_diverged_path(&planned.path)?;
+            }
+            Ok(true) => {
+                let bytes = &planned.edit.bytes;
+                report.record_update(&Change {
+                    removed: planned.size - planned.edit.offset,
+                    added: bytes.len() as u64,
+                    eol: if bytes.ends_with(eol::CRLF) {
+                        eol::CRLF
+                    } else {
+                        eol::LF
+                    },
+                    encoding: "",
+                    last_line: None,
+                    planned: None,
+                });
+                printer.write_updated_path(&planned.path, args.dry_run)?;
+            }
+            Err(err) => {
+                let result = FileResult::WalkError(Some(planned.path), err);
+                report.file_count += 1;
+                report.record(&result);
+                if !args.summarize_errors {
+                    printer.write_file_result(&result, args.dry_run)?;
+                }
+            }
+        }
+    }
+
+    report.elapsed = start.elapsed();
+    print_summary(&mut printer, &report, args)?;
+
+    Ok(report)
+}
+
+/// Adds the missing final end-of-lines to the entries of a zip archive, and rewrites it
+/// unless in dry-run mode.
+fn run_archive(path: &Path, args: &Args) -> Result<Report> {
+    let start = Instant::now();
+    let mut archive = zip::Archive::parse(&fs::read(path)?)
+        .map_err(|err| format!("invalid archive {}: {}", path.display(), err))?;
+
+    let mut printer = Printer::new(args.color_choice())
+        .with_header_style(args.header_style)
+        .with_theme(args.theme)
+        .with_path_separator(args.path_separator)
+        .with_thousands_separator(args.thousands_separator.clone());
+
+    let mut report = Report::default();
+
+    for entry in archive
+        .entries
+        .iter_mut()
+        .filter(|entry| !entry.is_directory())
+    {
+        let entry_path = path.join(String::from_utf8_lossy(&entry.name).as_ref());
+
+        let Some(content) = entry.content() else {
+            report.file_count += 1;
+            report.compressed_count += 1;
+            continue;
+        };
+
+        if content.contains(&0) {
+            report.file_count += 1;
+            report.binary_count += 1;
+            continue;
+        }
+
+        let Some((edit, change)) = plan_content(content, &entry_path, args)? else {
+            report.file_count += 1;
+            continue;
+        };
+
+        let mut content = content.to_vec();
+        edit.apply_to(&mut content);
+        if u32::try_from(content.len()).is_err() {
+            return Err(format!("{} is too large", entry_path.display()).into());
+        }
+
+        entry.set_content(content);
+        report.record_update(&change);
+        printer.write_updated_path(&entry_path, args.dry_run)?;
+    }
+
+    if report.updated_count != 0 && !args.dry_run {
+        let mut content = Vec::new();
+        archive.write(&mut content)?;
+        rewrite_atomically(path, &TailEdit::rewrite(content), args)?;
+    }
+
+    report.elapsed = start.elapsed();
+    print_summary(&mut printer, &report, args)?;
+
+    Ok(report)
+}
+
+/// Makes a change read from a plan, unless the file diverged from it since then, in which
+/// case `false` is returned.
+fn apply_planned(planned: &plan::PlannedEdit, args: &Args) -> Result<bool> {
+    let mut file = File::options()
+        .read(true)
+        .write(!args.dry_run && !args.atomic)
+        .open(&planned.path)?;
+
+    // The end of the file must be the one the plan was made from
+    let len = file.seek(SeekFrom::End(0))?;
+    if len != planned.size || plan::checksum(&mut file, planned.edit.offset)? != planned.checksum {
+        return Ok(false);
+    }
+
+    // Appending to a file which gained an end-of-line since then would add a blank line
+    if planned.edit.offset == len && eol::ends_with(&mut file, len, eol::LF)? {
+        return Ok(false);
+    }
+
+    if args.dry_run {
+        return Ok(true);
+    }
+
+    let atomic = rewrites_atomically(&planned.path, args);
+    if atomic != args.atomic {
+        drop(file);
+        file = File::options().write(true).open(&planned.path)?;
+    }
+
+    write_edit(file, &planned.path, &planned.edit, args, atomic)?;
+    Ok(true)
+}
+
+/// Runs the scan several times in dry-run mode, and prints the elapsed times.
+fn benchmark(
+    args: &Args,
+    walker: Option<WalkBuilder>,
+    runs: u64,
+    printer: &mut Printer,
+) -> Result<Report> {
+    let mut bench_args = args.clone();
+    bench_args.dry_run = true;
+    bench_args.format = Format::Text;
+
+    let mut report = walk_files(&bench_args, walker, |_, _| Ok(()))?;
+    let mut times = vec![report.elapsed];
+
+    for _ in 1..runs {
+        report = walk_files(&bench_args, build_walker(&bench_args)?, |_, _| Ok(()))?;
+        times.push(report.elapsed);
+    }
+
+    times.sort();
+
+    printer.write_count("benchmark runs", runs as usize)?;
+    printer.write_count("total files", report.file_count)?;
+    printer.write_stat("min time", format_args!("{:.3}s", times[0].as_secs_f64()))?;
+    printer.write_stat(
+        "median time",
+        format_args!("{:.3}s", times[times.len() / 2].as_secs_f64()),
+    )?;
+    printer.write_stat(
+        "max time",
+        format_args!("{:.3}s", times[times.len() - 1].as_secs_f64()),
+    )?;
+
+    Ok(report)
+}
+
+/// Checks how many files would be updated, and asks for confirmation if there are
+/// more than `limit`.
+fn confirm_changes(args: &Args, walker: Option<WalkBuilder>, limit: usize) -> Result<()> {
+    let mut check_args = args.clone();
+    check_args.dry_run = true;
+    check_args.format = Format::Text;
+
+    let count = walk_files(&check_args, walker, |_, _| Ok(()))?.updated_count;
+    if count <= limit {
+        return Ok(());
+    }
+
+    if !io::stdin().is_terminal() || !io::stderr().is_terminal() {
+        return Err(format!(
+            "{} files would be updated, which is over the limit of {}, pass --yes to confirm",
+            count, limit
+        )
+        .into());
+    }
+
+    eprint!("{} files would be updated, continue? [y/N] ", count);
+    io::stderr().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+
+    match answer.trim() {
+        "y" | "Y" | "yes" => Ok(()),
+        _ => Err("Aborted, no files were changed".into()),
+    }
+}
+
+/// Counts the files the walker would process, without opening them.
+fn count_files(args: &Args, walker: &WalkBuilder) -> usize {
+    let count = AtomicUsize::new(0);
+
+    walker.build_parallel().run(|| {
+        let count = &count;
+        Box::new(move |entry| {
+            if let Ok(entry) = entry {
+                if is_file(&entry, args)
+                    && matches_ext(&entry, args)
+                    && matches_extensionless(&entry, args)
+                {
+                    count.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            Continue
+        })
+    });
+
+    count.into_inner()
+}
+
+/// Flushes stdout when --flush-interval elapsed since it was last flushed.
+fn flush_if_due(args: &Args, flushed: &mut Instant) -> io::Result<()> {
+    if args.flush_interval == 0 || flushed.elapsed() < Duration::from_millis(args.flush_interval) {
+        return Ok(());
+    }
+
+    *flushed = Instant::now();
+    io::stdout().flush()
+}
+
+/// Processes the files found by the walker, passing each result to `on_result` as soon as it is available,
+/// with the time taken to process the file when it was.
+///
+/// Nothing is printed here: the returned report holds the aggregate counts, and the
+/// per-file outcomes are only given to `on_result`, on which the CLI layers the printer.
+fn walk_files(
+    args: &Args,
+    walker: Option<WalkBuilder>,
+    mut on_result: impl FnMut(&FileResult, Option<Duration>) -> Result<()> + Send,
+) -> Result<Report> {
+    let start = Instant::now();
+
+    let Some(walker) = walker else {
+        return Ok(Report::default());
+    };
+
+    log::info!(
+        "walk started ({})",
+        if args.no_parallel {
+            "sequential"
+        } else {
+            "parallel"
+        }
+    );
+
+    // Number of matching files found, including the ones refused over --max-matches
+    let matched = AtomicUsize::new(0);
+    let over_max = || {
+        args.max_matches
+            .is_some_and(|max| matched.load(Ordering::Relaxed) > max)
+    };
+
+    let mut flushed = Instant::now();
+
+    let mut report = if args.no_parallel {
+        let mut report = Report::default();
+
+        for entry in walker.build() {
+            visit_entry(entry, args, &matched, |result, duration| {
+                report.record(&result);
+                on_result(&result, duration)
+            })?;
+
+            progress::report_if_requested(&report, start);
+            flush_if_due(args, &mut flushed)?;
+
+            if (args.fail_fast && report.error_count != 0) || over_max() {
+                break;
+            }
+        }
+
+        report
+    } else {
+        thread::scope(|scope| {
+            let (tx, rx) = mpsc::channel::<(FileResult, Option<Duration>)>();
+
+            let consumer = scope.spawn(move || -> Result<Report> {
+                let mut report = Report::default();
+
+                loop {
+                    match rx.recv_timeout(PROGRESS_POLL_INTERVAL) {
+                        Ok((result, duration)) => {
+                            report.record(&result);
+                            on_result(&result, duration)?;
+
+                            // Dropping the receiver stops the walk
+                            if args.fail_fast && result.error().is_some() {
+                                break;
+                            }
+                        }
+                        Err(RecvTimeoutError::Timeout) => {}
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+
+                    progress::report_if_requested(&report, start);
+                    flush_if_due(args, &mut flushed)?;
+                }
+
+                Ok(report)
+            });
+
+            walker.build_parallel().run(|| {
+                let tx = tx.clone();
+
+                let matched = &matched;
+                let over_max = &over_max;
+
+                Box::new(move |entry| {
+                    let sent = visit_entry(entry, args, matched, |result, duration| {
+                        tx.send((result, duration))
+                            .map_err(|_| "the results consumer stopped".into())
+                    });
+
+                    // The consumer only stops early on error, which it reports itself,
+                    // or with --fail-fast
+                    if sent.is_ok() && !over_max() {
+                        Continue
+                    } else {
+                        Quit
+                    }
+                })
+            });
+
+            drop(tx);
+            consumer.join().unwrap()
+        })?
+    };
+
+    report.max_matches_reached = over_max();
+    report.elapsed = start.elapsed();
+
+    log::info!(
+        "walk finished: {} files, {} updated, {} errors in {:?}",
+        report.file_count,
+        report.updated_count,
+        report.error_count,
+        report.elapsed
+    );
+
+    Ok(report)
+}
+
+/// Processes a single entry found by the walker, passing its results to `emit`, with
+/// the time taken to process the file when it was. Matching files are counted in
+/// `matched`, and ignored once over --max-matches.
+fn visit_entry(
+    entry: std::result::Result<DirEntry, ignore::Error>,
+    args: &Args,
+    matched: &AtomicUsize,
+    mut emit: impl FnMut(FileResult, Option<Duration>) -> Result<()>,
+) -> Result<()> {
+    match entry {
+        Ok(entry) if !matches_ext(&entry, args) || !matches_extensionless(&entry, args) => Ok(()),
+        Ok(entry) if args.no_dereference && is_symlink(&entry) => {
+            if entry.path().is_file() {
+                emit(FileResult::SkippedSymlink(entry), None)?;
+            }
+            Ok(())
+        }
+        Ok(entry) if is_directory_reparse_point(&entry) => {
+            emit(FileResult::SkippedSymlink(entry), None)
+        }
+        Ok(entry) if args.resumed.contains(entry.path()) => {
+            emit(FileResult::SkippedResumed(entry), None)
+        }
+        Ok(entry) if is_file(&entry, args) => {
+            let index = matched.fetch_add(1, Ordering::Relaxed);
+            if args.max_matches.is_some_and(|max| index >= max) {
+                return Ok(());
+            }
+
+            let started = Instant::now();
+            let (result, long_line) = process_entry(entry, args);
+            let duration = started.elapsed();
+
+            log::trace!(
+                "{}: {} in {:?}",
+                result.path().unwrap_or(Path::new("")).display(),
+                result.status(),
+                duration
+            );
+
+            let warning = long_line
+                .zip(result.path())
+                .map(|(length, path)| FileResult::LongFinalLine(path.to_path_buf(), length));
+
+            emit(result, Some(duration))?;
+            match warning {
+                Some(warning) => emit(warning, None),
+                None => Ok(()),
+            }
+        }
+        Ok(_) => Ok(()),
+        Err(err) => emit(walk_error(err), None),
+    }
+}
+
+/// Returns whether the entry is a symlink, including when given as a path to search, as
+/// the walker follows these.
+fn is_symlink(entry: &DirEntry) -> bool {
+    entry.path_is_symlink()
+        || (entry.depth() == 0
+            && fs::symlink_metadata(entry.path()).is_ok_and(|m| m.file_type().is_symlink()))
+}
+
+/// Returns whether the entry is a file to process, which includes symlinks to files
+/// unless --no-dereference is given.
+fn is_file(entry: &DirEntry, args: &Args) -> bool {
+    entry.file_type().is_some_and(|ft| ft.is_file())
+        || (entry.path_is_symlink() && !args.no_dereference && entry.path().is_file())
+}
+
+/// Filters files by extension when only extensions are given, as the globs take care of it otherwise.
+fn matches_ext(entry: &DirEntry, args: &Args) -> bool {
+    if args.ext.is_empty() || args.has_globs() || entry.file_type().is_some_and(|ft| ft.is_dir()) {
+        return true;
+    }
+
+    has_ext(entry.path(), args)
+}
+
+/// Returns whether the path has one of the extensions given with --ext.
+fn has_ext(path: &Path, args: &Args) -> bool {
+    path.extension().is_some_and(|path_ext| {
+        args.ext.iter().any(|ext| {
+            let ext = ext.trim_start_matches('.');
+            if args.ignore_case {
+                path_ext.eq_ignore_ascii_case(ext)
+            } else {
+                path_ext == ext
+            }
+        })
+    })
+}
+
+/// Filters out files with an extension when only extensionless files are wanted.
+fn matches_extensionless(entry: &DirEntry, args: &Args) -> bool {
+    !args.only_extensionless
+        || entry.file_type().is_some_and(|ft| ft.is_dir())
+        || entry.path().extension().is_none()
+}
+
+/// Processes a file, and returns its result with the length of its last line when it is
+/// over --warn-long-final-line.
+fn process_entry(entry: DirEntry, args: &Args) -> (FileResult, Option<u64>) {
+    // The metadata of symlinks is the one of their target
+    if args.max_filesize.is_some() || args.min_filesize.is_some() {
+        match fs::metadata(entry.path()) {
+            Ok(metadata) if args.max_filesize.is_some_and(|max| metadata.len() > max) => {
+                return (FileResult::SkippedTooLarge(entry), None);
+            }
+            Ok(metadata) if args.min_filesize.is_some_and(|min| metadata.len() < min) => {
+                return (FileResult::SkippedTooSmall(entry), None);
+            }
+            Ok(_) => {}
+            Err(err) => return (FileResult::FileError(entry, err.into()), None),
+        }
+    }
+
+    let atomic = rewrites_atomically(entry.path(), args);
+    let hardlinked = args.atomic && !atomic;
+
+    let outcome = panic::catch_unwind(AssertUnwindSafe(|| process(&entry, args, atomic)));
+
+    let (outcome, long_line) = match outcome {
+        Ok(Ok((outcome, long_line))) => (outcome, long_line),
+        Ok(Err(err)) => return (FileResult::FileError(entry, err), None),
+        Err(payload) => {
+            return (
+                FileResult::Panicked(entry, panic_message(payload).into()),
+                None,
+            )
+        }
+    };
+
+    let result = match outcome {
+        Outcome::Updated(change) if hardlinked => FileResult::UpdatedInPlace(entry, change),
+        Outcome::Updated(change) => FileResult::UpdatedFile(entry, change),
+        Outcome::UpToDate(detected) => FileResult::UpToDateFile(entry, detected),
+        Outcome::Binary => FileResult::SkippedBinary(entry),
+    };
+
+    (result, long_line)
+}
+
+/// Extracts the message of a caught panic.
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast::<&'static str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "unknown panic".to_string(),
+        },
+    }
+}
+
+fn walk_error(err: ignore::Error) -> FileResult {
+    match err {
+        ignore::Error::WithDepth { err, .. } => walk_error(*err),
+        ignore::Error::WithPath { path, err } if path.is_dir() => {
+            FileResult::UnreadableDirectory(path, err)
+        }
+        ignore::Error::WithPath { path, err } => FileResult::WalkError(Some(path), err),
+        ignore::Error::Loop { ref child, .. } => {
+            FileResult::WalkError(Some(child.clone()), err.into())
+        }
+        err => FileResult::WalkError(None, err.into()),
+    }
+}
+
+/// Returns the globs given on the command line, followed by the ones of the glob files.
+fn read_globs(args: &Args) -> Result<Vec<String>> {
+    let mut globs = args.glob.clone();
+    for path in &args.glob_file {
+        globs.extend(glob::read_file(path)?);
+    }
+    Ok(globs)
+}
+
+/// Builds the overrides matching the globs and the extensions, unless there are only
+/// extensions, which are matched directly in the walker callback.
+fn build_overrides(args: &Args) -> Result<Option<Override>> {
+    if !args.has_globs() {
+        return Ok(None);
+    }
+
+    let mut override_builder = OverrideBuilder::new(paths::current_dir()?);
+    override_builder.case_insensitive(args.ignore_case)?;
+
+    for glob in &read_globs(args)? {
+        for pattern in glob::patterns(glob) {
+            override_builder.add(&pattern)?;
+        }
+    }
+
+    for ext in &args.ext {
+        override_builder.add(&format!("*.{}", ext.trim_start_matches('.')))?;
+    }
+
+    Ok(Some(override_builder.build()?))
+}
+
+fn build_walker(args: &Args) -> Result<Option<WalkBuilder>> {
+    let overrides = build_overrides(args)?;
+
+    let (roots, nested) = match args.since {
+        Some(ref reference) => (
+            git_roots(git::changed_files(reference)?, args, overrides.as_ref())?,
+            Vec::new(),
+        ),
+        None if args.tracked => (
+            git_roots(git::tracked_files()?, args, overrides.as_ref())?,
+            Vec::new(),
+        ),
+        None => {
+            let roots = collect_roots(&args.paths)?;
+            if !args.force {
+                check_filesystem_roots(&roots)?;
+            }
+            let nested = nested_roots(&roots);
+            let roots = roots
+                .into_iter()
+                .map(|root| resolve_root(root, args))
+                .collect();
+            (roots, nested)
+        }
+    };
+
+    log::debug!("search roots: {:?}", roots);
+
+    let Some((first_root, other_roots)) = roots.split_first() else {
+        return Ok(None);
+    };
+
+    let mut builder = WalkBuilder::new(first_root);
+    for path in other_roots {
+        builder.add(path);
+    }
+
+    if let Some(overrides) = overrides {
+        builder.overrides(overrides);
+    }
+
+    if args.no_ignore {
+        builder
+            .ignore(false)
+            .git_ignore(false)
+            .git_global(false)
+            .parents(false);
+    }
+
+    if args.no_parent_ignore {
+        builder.parents(false);
+    }
+
+    if args.hidden || args.hidden_files {
+        builder.hidden(false);
+    }
+
+    if args.no_recursive {
+        builder.max_depth(Some(1));
+    }
+
+    // Only one filter can be set, which prunes directories for all the options
+    if args.skip_remote || args.hidden_files || !nested.is_empty() {
+        let skip_remote = args.skip_remote;
+        let skip_hidden_dirs = args.hidden_files;
+
+        builder.filter_entry(move |entry| {
+            if entry.depth() == 0 {
+                return true;
+            }
+
+            // Nested roots are walked on their own, even where the enclosing walk would
+            // ignore them, so the enclosing walk must not visit them a second time
+            if !nested.is_empty()
+                && nested
+                    .iter()
+                    .any(|root| root.file_name() == Some(entry.file_name()))
+                && fs::canonicalize(entry.path()).is_ok_and(|path| nested.contains(&path))
+            {
+                return false;
+            }
+
+            if !entry.file_type().is_some_and(|ft| ft.is_dir()) {
+                return true;
+            }
+
+            if skip_hidden_dirs && entry.file_name().to_string_lossy().starts_with('.') {
+                return false;
+            }
+
+            let remote = skip_remote && mounts::is_remote(entry.path()) == Some(true);
+            if remote {
+                log::info!("skipping remote mount {}", entry.path().display());
+            }
+            !remote
+        });
+    }
+
+    if args.no_parallel {
+        builder.sort_by_file_name(|a, b| a.cmp(b));
+    }
+
+    if let Some(threads) = args.threads.or(args.io_bound.then_some(IO_BOUND_THREADS)) {
+        builder.threads(threads);
+    }
+
+    Ok(Some(builder))
+}
+
+/// Keeps the files listed by git which are under the search paths.
+/// These are given directly to the walker, so the globs need to be applied here.
+fn git_roots(
+    files: Vec<PathBuf>,
+    args: &Args,
+    overrides: Option<&Override>,
+) -> Result<Vec<PathBuf>> {
+    let search_paths = collect_roots(&args.paths)?
+        .into_iter()
+        .map(fs::canonicalize)
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let current_dir = paths::current_dir()?;
+
+    Ok(files
+        .into_iter()
+        .filter(|path| search_paths.iter().any(|root| path.starts_with(root)))
+        .filter(|path| overrides.is_none_or(|o| !o.matched(path, false).is_ignore()))
+        .map(|path| match path.strip_prefix(&current_dir) {
+            Ok(relative) => Path::new(".").join(relative),
+            Err(_) => path,
+        })
+        .collect())
+}
+
+/// Returns whether a file is to be rewritten atomically. Hardlinked files are edited
+/// in place even with --atomic, as renaming a new file over a hardlink would detach it
+/// from its other links.
+fn rewrites_atomically(path: &Path, args: &Args) -> bool {
+    args.atomic && !fs::metadata(path).is_ok_and(|m| is_hardlinked(&m))
+}
+
+#[cfg(unix)]
+fn is_hardlinked(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    metadata.nlink() > 1
+}
+
+#[cfg(not(unix))]
+fn is_hardlinked(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+/// Junctions and directory symlinks are not followed by the walker, so report them
+/// as skipped instead of silently leaving out their contents.
+#[cfg(windows)]
+fn is_directory_reparse_point(entry: &DirEntry) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    const FILE_ATTRIBUTE_REPARSE_POINT: u32 = 0x400;
+
+    entry.depth() > 0
+        && entry.path_is_symlink()
+        && entry.path().is_dir()
+        && entry
+            .metadata()
+            .is_ok_and(|metadata| metadata.file_attributes() & FILE_ATTRIBUTE_REPARSE_POINT != 0)
+}
+
+#[cfg(not(windows))]
+fn is_directory_reparse_point(_entry: &DirEntry) -> bool {
+    false
+}
+
+/// Drops the paths which don't exist, and the ones given more than once.
+///
+/// Roots nested in another one are kept, as the walk of the enclosing root may leave
+/// them out, for instance when they're ignored. See `nested_roots`.
+fn collect_roots(paths: &[String]) -> Result<Vec<&str>> {
+    if paths.is_empty() {
+        return Err("no path to search was given".into());
+    }
+
+    let mut candidates = Vec::new();
+
+    for path in paths {
+        match fs::canonicalize(path) {
+            Ok(canonical) => candidates.push((path.as_str(), canonical)),
+            Err(err) => eprintln!("warning: skipping {}: {}", path, err),
+        }
+    }
+
+    let roots: Vec<&str> = candidates
+        .iter()
+        .enumerate()
+        .filter(|(index, (_, canonical))| {
+            !candidates[..*index]
+                .iter()
+                .any(|(_, other)| other == canonical)
+        })
+        .map(|(_, (path, _))| *path)
+        .collect();
+
+    if roots.is_empty() {
+        return Err("none of the paths to search could be found".into());
+    }
+
+    Ok(roots)
+}
+
+/// Returns the canonical paths of the roots which are inside another root, which the
+/// walk of the enclosing root skips.
+fn nested_roots(roots: &[&str]) -> Vec<PathBuf> {
+    let canonical = roots
+        .iter()
+        .filter_map(|root| fs::canonicalize(root).ok())
+        .collect::<Vec<_>>();
+
+    canonical
+        .iter()
+        .filter(|path| {
+            canonical
+                .iter()
+                .any(|other| other != *path && path.starts_with(other))
+        })
+        .cloned()
+        .collect()
+}
+
+/// Replaces a symlinked directory root with its target, unless --no-resolve-roots or
+/// --no-dereference is given.
+fn resolve_root(root: &str, args: &Args) -> PathBuf {
+    let is_symlink = fs::symlink_metadata(root).is_ok_and(|m| m.file_type().is_symlink());
+
+    if is_symlink && !args.no_resolve_roots && !args.no_dereference && Path::new(root).is_dir() {
+        if let Ok(target) = fs::canonicalize(root) {
+            return target;
+        }
+    }
+
+    PathBuf::from(root)
+}
+
+/// Refuses to walk a whole file system, which is most likely a mistake.
+fn check_filesystem_roots(roots: &[&str]) -> Result<()> {
+    for root in roots {
+        if fs::canonicalize(root).is_ok_and(|path| path.parent().is_none()) {
+            return Err(format!(
+                "refusing to process the whole file system at {}, pass --force if this is intended",
+                root
+            )
+            .into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Processes a file, and returns the outcome with the length of its last line when it is
+/// over --warn-long-final-line.
+fn process(entry: &DirEntry, args: &Args, atomic: bool) -> Result<(Outcome, Option<u64>)> {
+    let mut file = File::options()
+        .read(true)
+        .write(!args.dry_run && !atomic)
+        .open(entry.path())?;
+
+    let plan = if args.rewrites_files() {
+        let mut content = Vec::new();
+        file.read_to_end(&mut content)?;
+
+        if content.contains(&0) {
+            return Ok((Outcome::Binary, None));
+        }
+
+        plan_rewrite(&content, entry.path(), args)?
+    } else if args.cleans_eof() {
+        if args.fix_all && contains_nul(&mut file)? {
+            return Ok((Outcome::Binary, None));
+        }
+
+        plan_clean_eof(&mut file, entry.path(), args)?
+    } else {
+        plan_tail_edit(&mut file, entry.path(), args)?
+    };
+
+    // Measured on the file as it was read, while its handle is at hand
+    let long_line = match args.warn_long_final_line {
+        Some(limit) => Some(final_line_length(&mut file, args.tail_chunk as usize)?)
+            .filter(|&length| length > limit),
+        None => None,
+    };
+
+    let Some((edit, mut change)) = plan else {
+        let detected = args
+            .format
+            .reports_detection()
+            .then(|| detect_ending(&mut file))
+            .transpose()?;
+        return Ok((Outcome::UpToDate(detected), long_line));
+    };
+
+    if args.dry_run {
+        if args.plan.is_some() {
+            change.planned = Some(plan::PlannedEdit {
+                path: entry.path().to_path_buf(),
+                size: file.seek(SeekFrom::End(0))?,
+                checksum: plan::checksum(&mut file, edit.offset)?,
+                edit,
+            });
+        }
+        return Ok((Outcome::Updated(change), long_line));
+    }
+
+    write_edit(file, entry.path(), &edit, args, atomic)?;
+    Ok((Outcome::Updated(change), long_line))
+}
+
+/// Writes an edit to the file opened for writing, or to a copy renamed over it in atomic
+/// mode, and reads it back when asked to.
+fn write_edit(
+    mut file: File,
+    path: &Path,
+    edit: &TailEdit,
+    args: &Args,
+    atomic: bool,
+) -> Result<()> {
+    if atomic {
+        drop(file);
+        rewrite_atomically(path, edit, args)?;
+    } else {
+        edit.apply(&mut file)?;
+        finish_write(&mut file, args)?;
+        drop(file);
+    }
+
+    if args.verify_writes {
+        edit.verify(path)?;
+    }
+
+    Ok(())
+}
+
+/// Plans the edit which terminates the last line of the file, if needed.
+fn plan_tail_edit(
+    file: &mut (impl Read + Seek),
+    path: &Path,
+    args: &Args,
+) -> Result<Option<(TailEdit, Change)>> {
+    let len = match file.seek(SeekFrom::End(-1)) {
+        Ok(pos) => pos + 1,
+        Err(err) => {
+            if file.seek(SeekFrom::End(0))? != 0 {
+                return Err(err.into());
+            }
+            if !args.newline_empty {
+                return Ok(None);
+            }
+            0
+        }
+    };
+
+    // A file holding nothing but a byte order mark is empty too
+    if (1..=encoding::MAX_BOM_LEN).contains(&len) && !args.newline_empty {
+        let mut content = Vec::new();
+        file.seek(SeekFrom::Start(0))?;
+        file.read_to_end(&mut content)?;
+
+        if encoding::is_bom_only(&content) {
+            return Ok(None);
+        }
+
+        file.seek(SeekFrom::Start(len - 1))?;
+    }
+
+    let mut byte = 0u8;
+    if len != 0 {
+        file.read_exact(slice::from_mut(&mut byte))?;
+    }
+
+    let (edit, newline) = match args.append_sequence {
+        Some(sequence) if eol::ends_with(file, len, sequence)? => return Ok(None),
+        Some(sequence) => (TailEdit::append(len, sequence), sequence),
+        None => match plan_newline(file, path, args, len, byte)? {
+            Some(planned) => planned,
+            None => return Ok(None),
+        },
+    };
+
+    // A unified diff can't show an arbitrary terminator, so only end-of-lines are shown
+    let last_line = if args.format.is_diff() && args.append_sequence.is_none() {
+        let (number, before) = read_last_line(file)?;
+        let mut after = before[..(edit.offset - (len - before.len() as u64)) as usize].to_vec();
+        after.extend_from_slice(&edit.bytes);
+
+        Some(LastLine {
+            number,
+            before,
+            after,
+        })
+    } else {
+        None
+    };
+
+    let change = Change {
+        removed: len - edit.offset,
+        added: edit.bytes.len() as u64,
+        eol: newline,
+        encoding: encoding::detect_file(file)?,
+        last_line,
+        planned: None,
+    };
+
+    Ok(Some((edit, change)))
+}
+
+/// Chooses the end-of-line which terminates the last line of the file, given its length
+/// and last byte, and plans the edit which adds it.
+fn plan_newline(
+    file: &mut (impl Read + Seek),
+    path: &Path,
+    args: &Args,
+    len: u64,
+    byte: u8,
+) -> Result<Option<(TailEdit, &'static [u8])>> {
+    if byte == b'\n' {
+        return Ok(None);
+    }
+
+    let unicode_separator = eol::ends_with_unicode_separator(file, len, byte)?;
+    if unicode_separator && !args.replace_unicode_separator {
+        return Ok(None);
+    }
+
+    let newline = args.eol.resolve(file)?;
+
+    let newline = match newline {
+        Some(newline) => newline,
+        None if byte == b'\r' => return Ok(None),
+        None => undetected_eol(path, args),
+    };
+
+    let edit = match (byte, newline) {
+        _ if unicode_separator => {
+            TailEdit::replace(len - eol::LINE_SEPARATOR.len() as u64, newline)
+        }
+        (b'\r', eol::CRLF) => TailEdit::append(len, b"\n"),
+        (b'\r', newline) => TailEdit::replace(len - 1, newline),
+        (_, newline) => TailEdit::append(len, newline),
+    };
+
+    Ok(Some((edit, newline)))
+}
+
+/// Chooses the end-of-line of a file in which auto mode found none to follow.
+///
+/// The first one found wins, in this order:
+/// 1. what git would use in the working tree (`eol` attribute, `core.autocrlf`, `core.eol`),
+/// 2. the `end_of_line` property of the .editorconfig files,
+/// 3. --fallback-eol, which defaults to the platform's end-of-line.
+fn undetected_eol(path: &Path, args: &Args) -> &'static [u8] {
+    git::working_tree_eol(path)
+        .or_else(|| editorconfig::eol(path))
+        .unwrap_or_else(|| args.fallback_eol.bytes())
+}
+
+/// Plans the edit which replaces the trailing whitespace and blank lines of the file with
+/// a single end-of-line, if needed.
+fn plan_clean_eof(
+    file: &mut (impl Read + Seek),
+    path: &Path,
+    args: &Args,
+) -> Result<Option<(TailEdit, Change)>> {
+    let len = file.seek(SeekFrom::End(0))?;
+    let mut offset = eol::trailing_whitespace_start(file, len, args.tail_chunk as usize)?;
+
+    // A byte order mark alone doesn't count as content
+    let nothing_before = offset == 0
+        || ((1..=encoding::MAX_BOM_LEN).contains(&offset) && {
+            let mut head = Vec::new();
+            file.seek(SeekFrom::Start(0))?;
+            file.take(offset).read_to_end(&mut head)?;
+            encoding::is_bom_only(&head)
+        });
+
+    let mut last_byte = 0u8;
+    if offset != 0 {
+        file.seek(SeekFrom::Start(offset - 1))?;
+        file.read_exact(slice::from_mut(&mut last_byte))?;
+    }
+
+    let unicode_separator = eol::ends_with_unicode_separator(file, offset, last_byte)?;
+    if unicode_separator && args.replace_unicode_separator {
+        offset -= eol::LINE_SEPARATOR.len() as u64;
+    }
+
+    let mut tail = Vec::with_capacity((len - offset) as usize);
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_to_end(&mut tail)?;
+
+    let newline = if unicode_separator && !args.replace_unicode_separator {
+        // The separator already terminates the last line
+        b""
+    } else if nothing_before && (tail == eol::LF || tail == eol::CRLF) {
+        // A file holding a single end-of-line is a single empty line, not an empty file
+        return Ok(None);
+    } else if nothing_before && !args.newline_empty && !tail.contains(&b'\n') {
+        b""
+    } else {
+        match args.eol.resolve(file)? {
+            Some(newline) => newline,
+            None => undetected_eol(path, args),
+        }
+    };
+
+    if tail == newline {
+        return Ok(None);
+    }
+
+    let edit = TailEdit::replace(offset, newline);
+    let change = Change {
+        removed: len - offset,
+        added: newline.len() as u64,
+        eol: if newline.is_empty() { eol::LF } else { newline },
+        encoding: encoding::detect_file(file)?,
+        last_line: None,
+        planned: None,
+    };
+
+    Ok(Some((edit, change)))
+}
+
+/// Plans the changes to content read in memory, picking the edit like `process` does.
+fn plan_content(content: &[u8], path: &Path, args: &Args) -> Result<Option<(TailEdit, Change)>> {
+    if args.rewrites_files() {
+        plan_rewrite(content, path, args)
+    } else if args.cleans_eof() {
+        plan_clean_eof(&mut io::Cursor::new(content), path, args)
+    } else {
+        plan_tail_edit(&mut io::Cursor::new(content), path, args)
+    }
+}
+
+/// Plans the rewrite of the whole file with trailing whitespace removed or normalized
+/// line endings, if needed.
+fn plan_rewrite(content: &[u8], path: &Path, args: &Args) -> Result<Option<(TailEdit, Change)>> {
+    if (content.is_empty() || encoding::is_bom_only(content)) && !args.newline_empty {
+        return Ok(None);
+    }
+
+    let newline = match args.convert_to() {
+        Some(newline) => newline,
+        None => match args.eol.resolve(&mut io::Cursor::new(content))? {
+            Some(newline) => newline,
+            None => undetected_eol(path, args),
+        },
+    };
+
+    let mut rewritten = if args.strip_trailing_whitespace {
+        convert::strip_trailing_whitespace(content, newline)
+    } else {
+        convert::Normalized::from(content)
+    };
+
+    if args.convert_to().is_some() {
+        let normalized = convert::normalize(&rewritten.content, newline);
+        rewritten.content = normalized.content;
+        rewritten.removed += normalized.removed;
+        rewritten.added += normalized.added;
+    }
+
+    if rewritten.content == content {
+        return Ok(None);
+    }
+
+    let change = Change {
+        removed: rewritten.removed,
+        added: rewritten.added,
+        eol: newline,
+        encoding: encoding::detect(content),
+        last_line: None,
+        planned: None,
+    };
+
+    Ok(Some((TailEdit::rewrite(rewritten.content), change)))
+}
+
+/// Returns the one-based number and the content of the last line of the file.
+fn read_last_line(file: &mut (impl Read + Seek)) -> io::Result<(u64, Vec<u8>)> {
+    file.seek(SeekFrom::Start(0))?;
+
+    let mut number = 1;
+    let mut text = Vec::new();
+
+    // Not nested: the last line is collected into its own vector
+    buffer::with_buffer(buffer::CHUNK_SIZE, |buf| loop {
+        let len = match file.read(buf) {
+            Ok(0) => return Ok(()),
+            Ok(len) => len,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+
+        let chunk = &buf[..len];
+        match chunk.iter().rposition(|&b| b == b'\n') {
+            Some(index) => {
+                number += chunk.iter().filter(|&&b| b == b'\n').count() as u64;
+                text.clear();
+                text.extend_from_slice(&chunk[index + 1..]);
+            }
+            None => text.extend_from_slice(chunk),
+        }
+    })?;
+
+    Ok((number, text))
+}
+
+/// Detects the encoding and the final end-of-line of a file.
+fn detect_ending(file: &mut (impl Read + Seek)) -> io::Result<Detected> {
+    let len = file.seek(SeekFrom::End(0))?;
+
+    let eol = if eol::ends_with(file, len, eol::CRLF)? {
+        Some(eol::CRLF)
+    } else if eol::ends_with(file, len, eol::LF)? {
+        Some(eol::LF)
+    } else {
+        None
+    };
+
+    Ok(Detected {
+        encoding: encoding::detect_file(file)?,
+        eol,
+    })
+}
+
+/// Returns whether the file contains a NUL byte, which marks it as binary.
+fn contains_nul(file: &mut File) -> io::Result<bool> {
+    file.seek(SeekFrom::Start(0))?;
+
+    // Not nested: each chunk is only searched for NUL bytes
+    buffer::with_buffer(buffer::CHUNK_SIZE, |buf| loop {
+        match file.read(buf) {
+            Ok(0) => return Ok(false),
+            Ok(len) if buf[..len].contains(&0) => return Ok(true),
+            Ok(_) => {}
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    })
+}
+
+/// Returns the length of the last line of the file, excluding its end-of-line, by reading
+/// backwards up to the previous line feed.
+fn final_line_length(file: &mut (impl Read + Seek), chunk_size: usize) -> io::Result<u64> {
+    let len = file.seek(SeekFrom::End(0))?;
+
+    // Not nested: the chunks are only searched for line feeds
+    buffer::with_buffer(chunk_size, |buf| {
+        let mut end = len;
+        let mut pos = len;
+
+        while pos > 0 {
+            let chunk_len = (pos.min(buf.len() as u64)) as usize;
+            pos -= chunk_len as u64;
+
+            file.seek(SeekFrom::Start(pos))?;
+            file.read_exact(&mut buf[..chunk_len])?;
+
+            let mut chunk = &buf[..chunk_len];
+
+            // The end-of-line of the last line itself doesn't count
+            if pos + chunk_len as u64 == len {
+                for terminator in [b'\n', b'\r'] {
+                    if let Some(rest) = chunk.strip_suffix(&[terminator]) {
+                        chunk = rest;
+                        end -= 1;
+                    }
+                }
+            }
+
+            if let Some(index) = chunk.iter().rposition(|&b| b == b'\n') {
+                return Ok(end - (pos + index as u64 + 1));
+            }
+        }
+
+        Ok(end)
+    })
+}
+
+/// Replaces the end of a file, starting at a given offset.
+struct TailEdit {
+    offset: u64,
+    bytes: Cow<'static, [u8]>,
+}
+
+impl TailEdit {
+    fn append(len: u64, bytes: &'static [u8]) -> TailEdit {
+        TailEdit {
+            offset: len,
+            bytes: Cow::Borrowed(bytes),
+        }
+    }
+
+    fn replace(offset: u64, bytes: &'static [u8]) -> TailEdit {
+        TailEdit {
+            offset,
+            bytes: Cow::Borrowed(bytes),
+        }
+    }
+
+    fn rewrite(content: Vec<u8>) -> TailEdit {
+        TailEdit {
+            offset: 0,
+            bytes: Cow::Owned(content),
+        }
+    }
+
+    fn apply_to(&self, content: &mut Vec<u8>) {
+        content.truncate(self.offset as usize);
+        content.extend_from_slice(&self.bytes);
+    }
+
+    fn apply(&self, file: &mut File) -> io::Result<()> {
+        file.set_len(self.offset)?;
+        file.seek(SeekFrom::Start(self.offset))?;
+        file.write_all(&self.bytes)
+    }
+
+    /// Reads the file again to check that it ends as expected after the edit.
+    fn verify(&self, path: &Path) -> Result<()> {
+        let mut file = File::open(path)?;
+        let len = file.seek(SeekFrom::End(0))?;
+
+        let mut tail = vec![0u8; self.bytes.len()];
+        if len == self.offset + self.bytes.len() as u64 {
+            file.seek(SeekFrom::Start(self.offset))?;
+            file.read_exact(&mut tail)?;
+        }
+
+        if len != self.offset + self.bytes.len() as u64 || tail != *self.bytes {
+            return Err("the file doesn't end as expected after writing it".into());
+        }
+
+        Ok(())
+    }
+}
+
+fn finish_write(file: &mut File, args: &Args) -> io::Result<()> {
+    if args.sync {
+        file.sync_all()
+    } else if args.no_sync {
+        Ok(())
+    } else {
+        file.flush()
+    }
+}
+
+fn rewrite_atomically(path: &Path, edit: &TailEdit, args: &Args) -> Result<()> {
+    // Renaming over a symlink would replace it, instead of its target
+    let target;
+    let path = if fs::symlink_metadata(path)?.file_type().is_symlink() {
+        target = fs::canonicalize(path)?;
+        &target
+    } else {
+        path
+    };
+
+    let file_name = path.file_name().ok_or("invalid file name")?;
+
+    let mut temp_name = file_name.to_os_string();
+    temp_name.push(".addeol.tmp");
+    let temp_path = path.with_file_name(temp_name);
+
+    let result = fs::copy(path, &temp_path)
+        .and_then(|_| {
+            let mut temp_file = File::options().write(true).open(&temp_path)?;
+            edit.apply(&mut temp_file)?;
+            finish_write(&mut temp_file, args)
+        })
+        .and_then(|_| fs::rename(&temp_path, path));
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+
+    Ok(result?)
+}
+
+fn print_result(
+    printer: &mut Printer,
+    result: &FileResult,
+    duration: Option<Duration>,
+    args: &Args,
+) -> Result<()> {
+    let visible = match result {
+        _ if args.summarize_errors && result.error().is_some() => false,
+        _ if args.errors_only => result.error().is_some(),
+        _ if args.list_missing => result.change().is_some() || result.error().is_some(),
+        FileResult::UpToDateFile(_, _) => args.list,
+        FileResult::SkippedTooLarge(_)
+        | FileResult::SkippedTooSmall(_)
+        | FileResult::SkippedSymlink(_)
+        | FileResult::SkippedBinary(_)
+        | FileResult::SkippedResumed(_) => args.list || args.show_skipped,
+        _ => true,
+    };
+
+    if args.porcelain {
+        if visible {
+            porcelain::write_result(&mut io::stdout(), result, args.path_separator)?;
+        }
+        return Ok(());
+    }
+
+    if visible {
+        printer.write_file_result(result, args.dry_run)?;
+    }
+
+    if args.format == Format::Events {
+        events::write_file(&mut io::stdout(), result, duration, args.path_separator)?;
+    }
+
+    if let Some(delimiter) = args.format.delimiter() {
+        csv::write_result(&mut io::stdout(), delimiter, result, args.path_separator)?;
+    }
+
+    if args.format.is_diff() {
+        if let FileResult::UpdatedFile(entry, change) | FileResult::UpdatedInPlace(entry, change) =
+            result
+        {
+            if args.format == Format::GitPatch {
+                diff::write_git_patch(
+                    &mut io::stdout(),
+                    &PathSeparator::Slash.apply(entry.path()),
+                    change,
+                )?;
+            } else {
+                diff::write_diff(
+                    &mut io::stdout(),
+                    &args.path_separator.apply(entry.path()),
+                    change,
+                )?;
+            }
+        }
+    }
+
+    if args.print0 {
+        if let FileResult::UpdatedFile(entry, _) | FileResult::UpdatedInPlace(entry, _) = result {
+            printer::write_path0(&mut io::stdout(), &args.path_separator.apply(entry.path()))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn print_oneline_summary(printer: &mut Printer, report: &Report, args: &Args) -> Result<()> {
+    io::stdout().flush()?;
+
+    printer.write_separator()?;
+    let line = match args.stat_format {
+        Some(ref format) => format.expand(report, printer, args.dry_run),
+        None => {
+            StatFormat::parse(template::DEFAULT_STAT_FORMAT)?.expand(report, printer, args.dry_run)
+        }
+    };
+    printer.write_oneline_summary(format_args!("{}", line))?;
+
+    Ok(())
+}
+
+/// Prints the error count for --errors-only, unless there was no error.
+fn print_error_summary(printer: &mut Printer, report: &Report, args: &Args) -> Result<()> {
+    if report.error_count == 0 {
+        return Ok(());
+    }
+
+    io::stdout().flush()?;
+
+    printer.write_separator()?;
+    printer.write_count("error count", report.error_count)?;
+
+    if args.summarize_errors {
+        write_errors_by_message(printer, report)?;
+    }
+
+    Ok(())
+}
+
+fn write_errors_by_message(printer: &mut Printer, report: &Report) -> Result<()> {
+    for (index, (message, count)) in report.error_messages.iter().enumerate() {
+        let count = printer.group_digits(*count);
+        printer.write_stat(
+            if index == 0 { "errors by message" } else { "" },
+            format_args!("{} {}", count, message.trim_start()),
+        )?;
+    }
+
+    Ok(())
+}
+
+fn print_summary(printer: &mut Printer, report: &Report, args: &Args) -> Result<()> {
+    io::stdout().flush()?;
+
+    printer.write_separator()?;
+
+    printer.write_count("total files", report.file_count)?;
+
+    printer.write_count(
+        if args.dry_run {
+            "files to be updated"
+        } else {
+            "updated files"
+        },
+        report.updated_count,
+    )?;
+
+    if args.rewrites_files() || args.cleans_eof() {
+        printer.write_count("bytes removed", report.bytes_removed as usize)?;
+        printer.write_count("bytes added", report.bytes_added as usize)?;
+    }
+
+    // Only auto mode picks the end-of-line per file, and only appends are broken down
+    let appends_eol =
+        !args.rewrites_files() && !args.cleans_eof() && args.append_sequence.is_none();
+    if args.dry_run && args.eol == Eol::Auto && appends_eol && report.updated_count != 0 {
+        printer.write_count("with LF to add", report.lf_count)?;
+        printer.write_count("with CRLF to add", report.crlf_count)?;
+    }
+
+    if report.in_place_count != 0 {
+        printer.write_count("hardlinks in place", report.in_place_count)?;
+    }
+
+    if report.too_large_count != 0 {
+        printer.write_count("skipped (too large)", report.too_large_count)?;
+    }
+
+    if report.diverged_count != 0 {
+        printer.write_count("diverged from plan", report.diverged_count)?;
+    }
+
+    if report.too_small_count != 0 {
+        printer.write_count("skipped (too small)", report.too_small_count)?;
+    }
+
+    if report.binary_count != 0 {
+        printer.write_count("skipped (binary)", report.binary_count)?;
+    }
+
+    if report.resumed_count != 0 {
+        printer.write_count("skipped (resumed)", report.resumed_count)?;
+    }
+
+    if report.compressed_count != 0 {
+        printer.write_count("skipped (compressed)", report.compressed_count)?;
+    }
+
+    if report.symlink_count != 0 {
+        printer.write_count("skipped (symlinks)", report.symlink_count)?;
+    }
+
+    if report.long_line_count != 0 {
+        printer.write_count("long final lines", report.long_line_count)?;
+    }
+
+    if report.panic_count != 0 {
+        printer.write_count("panics", report.panic_count)?;
+    }
+
+    if report.unreadable_dir_count != 0 {
+        printer.write_count("unreadable directories", report.unreadable_dir_count)?;
+    }
+
+    if report.error_count != 0 {
+        printer.write_count("error count", report.error_count)?;
+    }
+
+    if report.max_matches_reached {
+        printer.write_count("max matches reached", args.max_matches.unwrap_or_default())?;
+    }
+
+    if args.summarize_errors {
+        write_errors_by_message(printer, report)?;
+    }
+
+    // Timings of tiny runs are mostly noise
+    let elapsed = report.elapsed.as_secs_f64();
+    if report.file_count >= 100 && elapsed >= 0.1 {
+        printer.write_stat("elapsed time", format_args!("{:.2}s", elapsed))?;
+        printer.write_stat(
+            "files per second",
+            format_args!("{:.0}", report.file_count as f64 / elapsed),
+        )?;
+    }
+
+    Ok(())
+}
+
+fn parse_size(value: &str) -> std::result::Result<u64, String> {
+    let (digits, multiplier) = match value.char_indices().last() {
+        Some((index, 'k' | 'K')) => (&value[..index], 1 << 10),
+        Some((index, 'm' | 'M')) => (&value[..index], 1 << 20),
+        Some((index, 'g' | 'G')) => (&value[..index], 1 << 30),
+        _ => (value, 1),
+    };
+
+    digits
+        .parse::<u64>()
+        .ok()
+        .and_then(|size| size.checked_mul(multiplier))
+        .ok_or_else(|| format!("invalid size: {}", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(extra: &[&str]) -> Args {
+        Cli::parse_from(["addeol", "--glob", "*"].iter().chain(extra)).into_args()
+    }
+
+    fn parse(args: &[&str]) -> Args {
+        Cli::parse_from(["addeol"].iter().chain(args)).into_args()
+    }
+
+    fn overrides(args: &[&str]) -> Override {
+        build_overrides(&parse(args)).unwrap().unwrap()
+    }
+
+    #[test]
+    fn parses_stdin_without_globs() {
+        assert!(Cli::try_parse_from(["addeol", "-"]).is_ok());
+    }
+
+    #[test]
+    fn no_color_beats_other_color_options() {
+        assert_eq!(
+            args(&["--no-color", "--color=always"]).color_choice(),
+            ColorChoice::Never
+        );
+        assert_eq!(
+            args(&["--no-color", "--force-color"]).color_choice(),
+            ColorChoice::Never
+        );
+    }
+
+    #[test]
+    fn force_color_beats_color_option() {
+        assert_eq!(
+            args(&["--force-color", "--color=never"]).color_choice(),
+            ColorChoice::Always
+        );
+    }
+
+    #[test]
+    fn matches_mixed_case_globs_with_ignore_case() {
+        let insensitive = overrides(&["--ignore-case", "--glob", "*.rs", "--ext", "TXT"]);
+        for path in ["Main.RS", "lib.rs", "a.Txt", "b.txt"] {
+            assert!(insensitive.matched(path, false).is_whitelist(), "{}", path);
+        }
+
+        let sensitive = overrides(&["--glob", "*.rs", "--ext", "TXT"]);
+        assert!(sensitive.matched("lib.rs", false).is_whitelist());
+        assert!(sensitive.matched("a.TXT", false).is_whitelist());
+        assert!(!sensitive.matched("Main.RS", false).is_whitelist());
+        assert!(!sensitive.matched("a.txt", false).is_whitelist());
+    }
+
+    #[test]
+    fn matches_mixed_case_extensions_with_ignore_case() {
+        let args = parse(&["--ignore-case", "--ext", ".RS"]);
+        assert!(has_ext(Path::new("Main.rs"), &args));
+        assert!(has_ext(Path::new("lib.Rs"), &args));
+        assert!(!has_ext(Path::new("lib.rsx"), &args));
+
+        let args = parse(&["--ext", "RS"]);
+        assert!(has_ext(Path::new("lib.RS"), &args));
+        assert!(!has_ext(Path::new("lib.rs"), &args));
+    }
+
+    /// Returns the content after the tail edit planned for it, if any.
+    fn tail_edit(content: &[u8], args: &Args) -> Option<Vec<u8>> {
+        let (edit, _) =
+            plan_tail_edit(&mut io::Cursor::new(content), Path::new("test.txt"), args).unwrap()?;
+
+        let mut content = content.to_vec();
+        edit.apply_to(&mut content);
+        Some(content)
+    }
+
+    #[test]
+    fn completes_lone_cr_to_detected_eol() {
+        let args = args(&["--eol", "auto"]);
+        assert_eq!(tail_edit(b"x\ny\r", &args).as_deref(), Some(&b"x\ny\n"[..]));
+        assert_eq!(
+            tail_edit(b"x\r\ny\r", &args).as_deref(),
+            Some(&b"x\r\ny\r\n"[..])
+        );
+    }
+
+    #[test]
+    fn keeps_lone_cr_without_other_eol_in_auto_mode() {
+        assert_eq!(tail_edit(b"y\r", &args(&["--eol", "auto"])), None);
+    }
+
+    #[test]
+    fn replaces_lone_cr_with_lf() {
+        let args = args(&["--eol", "lf"]);
+        assert_eq!(tail_edit(b"x\ny\r", &args).as_deref(), Some(&b"x\ny\n"[..]));
+        assert_eq!(
+            tail_edit(b"x\r\ny\r", &args).as_deref(),
+            Some(&b"x\r\ny\n"[..])
+        );
+        assert_eq!(tail_edit(b"y\r", &args).as_deref(), Some(&b"y\n"[..]));
+    }
+
+    #[test]
+    fn completes_content_after_bom() {
+        let args = args(&["--eol", "lf"]);
+        let content = b"\xEF\xBB\xBFx";
+
+        let (_, change) =
+            plan_tail_edit(&mut io::Cursor::new(content), Path::new("test.txt"), &args)
+                .unwrap()
+                .unwrap();
+        assert_eq!(change.encoding, "utf-8-bom");
+        assert_eq!(
+            tail_edit(content, &args).as_deref(),
+            Some(&b"\xEF\xBB\xBFx\n"[..])
+        );
+    }
+
+    #[test]
+    fn treats_bom_only_files_as_empty() {
+        let lf = args(&["--eol", "lf"]);
+        assert_eq!(tail_edit(b"\xEF\xBB\xBF", &lf), None);
+        assert_eq!(tail_edit(b"\xFF\xFE", &lf), None);
+
+        let newline_empty = args(&["--eol", "lf", "--newline-empty"]);
+        assert_eq!(
+            tail_edit(b"\xEF\xBB\xBF", &newline_empty).as_deref(),
+            Some(&b"\xEF\xBB\xBF\n"[..])
+        );
+    }
+
+    #[test]
+    fn keeps_unicode_separators() {
+        let lf = args(&["--eol", "lf"]);
+        assert_eq!(tail_edit("x\u{2028}".as_bytes(), &lf), None);
+        assert_eq!(tail_edit("x\u{2029}".as_bytes(), &lf), None);
+    }
+
+    #[test]
+    fn replaces_unicode_separators_when_asked_to() {
+        let lf = args(&["--eol", "lf", "--replace-unicode-separator"]);
+        assert_eq!(
+            tail_edit("x\u{2028}".as_bytes(), &lf).as_deref(),
+            Some(&b"x\n"[..])
+        );
+        assert_eq!(
+            tail_edit("x\u{2029}".as_bytes(), &lf).as_deref(),
+            Some(&b"x\n"[..])
+        );
+    }
+
+    /// Returns the content after the end-of-file cleanup planned for it, if any.
+    fn clean_eof(content: &[u8], args: &Args) -> Option<Vec<u8>> {
+        let (edit, _) =
+            plan_clean_eof(&mut io::Cursor::new(content), Path::new("test.txt"), args).unwrap()?;
+
+        let mut content = content.to_vec();
+        edit.apply_to(&mut content);
+        Some(content)
+    }
+
+    #[test]
+    fn cleans_bom_only_files_as_empty() {
+        for option in ["--clean-eof", "--fix-all"] {
+            let args = args(&["--eol", "lf", option]);
+            assert_eq!(clean_eof(b"\xEF\xBB\xBF", &args), None);
+            assert_eq!(
+                clean_eof(b"\xEF\xBB\xBF \t", &args).as_deref(),
+                Some(&b"\xEF\xBB\xBF"[..])
+            );
+        }
+    }
+
+    #[test]
+    fn cleans_eof_after_unicode_separators() {
+        let lf = args(&["--eol", "lf", "--clean-eof"]);
+        assert_eq!(clean_eof("x\u{2028}".as_bytes(), &lf), None);
+        assert_eq!(
+            clean_eof("x\u{2029} \n".as_bytes(), &lf).as_deref(),
+            Some("x\u{2029}".as_bytes())
+        );
+
+        let replace = args(&["--eol", "lf", "--clean-eof", "--replace-unicode-separator"]);
+        assert_eq!(
+            clean_eof("x\u{2028}".as_bytes(), &replace).as_deref(),
+            Some(&b"x\n"[..])
+        );
+    }
+
+    #[test]
+    fn keeps_single_eol_files_intact() {
+        for eol in ["lf", "crlf", "auto"] {
+            let args = args(&["--eol", eol, "--clean-eof"]);
+            assert_eq!(clean_eof(b"\n", &args), None);
+            assert_eq!(clean_eof(b"\r\n", &args), None);
+        }
+    }
+
+    #[test]
+    fn collapses_blank_lines_into_one_eol() {
+        let args = args(&["--eol", "auto", "--clean-eof"]);
+        assert_eq!(clean_eof(b"\n\n", &args).as_deref(), Some(&b"\n"[..]));
+        assert_eq!(clean_eof(b"\r\n\r\n", &args).as_deref(), Some(&b"\r\n"[..]));
+        assert_eq!(clean_eof(b"x\n \n\n", &args).as_deref(), Some(&b"x\n"[..]));
+        assert_eq!(clean_eof(b"x\n", &args), None);
+    }
+
+    #[test]
+    fn normalizes_the_final_eol_with_clean_eof() {
+        let crlf = args(&["--eol", "crlf", "--clean-eof"]);
+        assert_eq!(clean_eof(b"x\n", &crlf).as_deref(), Some(&b"x\r\n"[..]));
+        assert_eq!(clean_eof(b"x\r\n", &crlf), None);
+
+        let lf = args(&["--eol", "lf", "--clean-eof"]);
+        assert_eq!(clean_eof(b"x\r\n", &lf).as_deref(), Some(&b"x\n"[..]));
+        assert_eq!(clean_eof(b"x\n", &lf), None);
+    }
+
+    fn stdin_output(content: &[u8], args: &Args) -> Vec<u8> {
+        let mut content = content.to_vec();
+        process_stdin(&mut content, Path::new("<stdin>"), args).unwrap();
+        content
+    }
+
+    #[test]
+    fn cleans_eof_of_stdin() {
+        let args = parse(&["--clean-eof", "--eol", "lf", "-"]);
+        assert_eq!(stdin_output(b"x  \n\n\n", &args), b"x\n");
+        assert_eq!(stdin_output(b"x", &args), b"x\n");
+        assert_eq!(stdin_output(b"x\n", &args), b"x\n");
+    }
+
+    #[test]
+    fn fixes_all_of_stdin() {
+        let args = parse(&["--fix-all", "--eol", "lf", "-"]);
+        assert_eq!(stdin_output(b"x \t\n \n\n", &args), b"x\n");
+        assert_eq!(stdin_output(b"x", &args), b"x\n");
+
+        let mut binary = b"x\0  \n\n".to_vec();
+        assert!(matches!(
+            process_stdin(&mut binary, Path::new("<stdin>"), &args),
+            Ok(Outcome::Binary)
+        ));
+        assert_eq!(binary, b"x\0  \n\n");
+    }
+
+    #[test]
+    fn applies_plans_only_to_unchanged_files() {
+        let path = env::temp_dir().join(format!("addeol-apply-{}.txt", process::id()));
+        let planned = plan::PlannedEdit {
+            path: path.clone(),
+            size: 3,
+            checksum: plan::checksum(&mut io::Cursor::new(b"abc"), 3).unwrap(),
+            edit: TailEdit::append(3, eol::LF),
+        };
+        let args = args(&[]);
+
+        // Same size, but now ending with an end-of-line
+        fs::write(&path, "ab\n").unwrap();
+        let diverged = apply_planned(&planned, &args);
+        let diverged_content = fs::read(&path);
+
+        fs::write(&path, "abc").unwrap();
+        let applied = apply_planned(&planned, &args);
+        let applied_content = fs::read(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert!(!diverged.unwrap());
+        assert_eq!(diverged_content.unwrap(), b"ab\n");
+        assert!(applied.unwrap());
+        assert_eq!(applied_content.unwrap(), b"abc\n");
+    }
+
+    #[test]
+    fn run_returns_the_outcome_of_each_file() {
+        let dir = env::temp_dir().join(format!("addeol-run-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("done.txt"), "a\n").unwrap();
+        fs::write(dir.join("todo.txt"), "a").unwrap();
+
+        let options = Options::parse(["--glob", "*.txt", "--dry-run", dir.to_str().unwrap()]);
+        let report = run(&options.unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+        let report = report.unwrap();
+
+        let mut outcomes = report
+            .outcomes
+            .iter()
+            .map(|outcome| {
+                let name = outcome.path.as_ref().unwrap().file_name().unwrap();
+                (name.to_str().unwrap(), outcome.status, outcome.change)
+            })
+            .collect::<Vec<_>>();
+        outcomes.sort();
+
+        assert_eq!(
+            outcomes,
+            [
+                ("done.txt", "up-to-date", None),
+                ("todo.txt", "updated", Some((0, 1)))
+            ]
+        );
+        assert_eq!(report.file_count, 2);
+        assert_eq!(report.updated_count, 1);
+    }
+
+    #[test]
+    fn run_rejects_command_line_only_modes() {
+        assert!(run(&Options::parse(["-"]).unwrap()).is_err());
+    }
+}
@@ -0,0 +1,79 @@
+use clap::ValueEnum;
+use log::{LevelFilter, Log, Metadata, Record};
+use std::io::{self, Write};
+use std::sync::OnceLock;
+use std::time::Instant;
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LogLevel {
+    /// No diagnostics
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    /// Everything, including a line per processed file
+    Trace,
+}
+
+/// Writes log records to stderr, with the time elapsed since the logger was installed.
+struct StderrLogger {
+    start: OnceLock<Instant>,
+}
+
+static LOGGER: StderrLogger = StderrLogger {
+    start: OnceLock::new(),
+};
+
+/// Sends diagnostics at the given level or above to stderr, including the ones of the
+/// walker. Nothing is installed when off, so that disabled logging costs nothing.
+///
+/// This uses the `log` facade rather than `tracing`, as the walker logs through it, and
+/// the start and end of the walk with a line per file don't call for nested spans.
+pub(crate) fn init(level: LogLevel) {
+    let filter = match level {
+        LogLevel::Off => return,
+        LogLevel::Error => LevelFilter::Error,
+        LogLevel::Warn => LevelFilter::Warn,
+        LogLevel::Info => LevelFilter::Info,
+        LogLevel::Debug => LevelFilter::Debug,
+        LogLevel::Trace => LevelFilter::Trace,
+    };
+
+    LOGGER.start.get_or_init(Instant::now);
+
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(filter);
+    }
+}
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let elapsed = self
+            .start
+            .get()
+            .map_or(0.0, |start| start.elapsed().as_secs_f64());
+
+        // Diagnostics are best effort, and must never fail the run
+        let _ = writeln!(
+            io::stderr().lock(),
+            "[{:>9.3}s {:<5} {}] {}",
+            elapsed,
+            record.level(),
+            record.target(),
+            record.args()
+        );
+    }
+
+    fn flush(&self) {
+        let _ = io::stderr().flush();
+    }
+}
@@ -0,0 +1,74 @@
+use std::path::Path;
+
+/// Returns whether the directory is on a network file system, or `None` when the file
+/// system type can't be determined.
+pub(crate) fn is_remote(path: &Path) -> Option<bool> {
+    let stat = statfs(path)?;
+    Some(is_remote_fs(&stat))
+}
+
+#[cfg(unix)]
+fn statfs(path: &Path) -> Option<libc::statfs> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+    let result = unsafe { libc::statfs(path.as_ptr(), &mut stat) };
+    (result == 0).then_some(stat)
+}
+
+#[cfg(not(unix))]
+fn statfs(_path: &Path) -> Option<()> {
+    None
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn is_remote_fs(stat: &libc::statfs) -> bool {
+    const REMOTE_MAGICS: &[u32] = &[
+        0x6969,     // NFS
+        0x517b,     // SMB
+        0xfe534d42, // SMB2
+        0xff534d42, // CIFS
+        0x564c,     // NCP
+        0x5346414f, // AFS
+        0x73757245, // Coda
+        0x00c36400, // Ceph
+        0x013111a8, // IBRIX
+        0x47504653, // GPFS
+    ];
+
+    REMOTE_MAGICS.contains(&(stat.f_type as u32))
+}
+
+#[cfg(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "openbsd"
+))]
+fn is_remote_fs(stat: &libc::statfs) -> bool {
+    (stat.f_flags as i64 & libc::MNT_LOCAL as i64) == 0
+}
+
+#[cfg(all(
+    unix,
+    not(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd",
+        target_os = "dragonfly",
+        target_os = "openbsd"
+    ))
+))]
+fn is_remote_fs(_stat: &libc::statfs) -> bool {
+    false
+}
+
+#[cfg(not(unix))]
+fn is_remote_fs(_stat: &()) -> bool {
+    false
+}
@@ -0,0 +1,81 @@
+use clap::ValueEnum;
+use std::borrow::Cow;
+use std::path::{Component, Path, PathBuf};
+use std::{fs, io};
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PathSeparator {
+    /// The platform's separator
+    Native,
+    /// Forward slashes, even on Windows
+    Slash,
+}
+
+impl PathSeparator {
+    /// Returns the path with its separators replaced as needed.
+    pub(crate) fn apply(self, path: &Path) -> Cow<'_, Path> {
+        if self == PathSeparator::Native || std::path::MAIN_SEPARATOR == '/' {
+            return Cow::Borrowed(path);
+        }
+
+        let text = path.to_string_lossy();
+        Cow::Owned(PathBuf::from(text.replace(std::path::MAIN_SEPARATOR, "/")))
+    }
+}
+
+/// Returns the current directory, with a clear error when it's not available anymore.
+pub(crate) fn current_dir() -> io::Result<PathBuf> {
+    std::env::current_dir().map_err(|err| {
+        io::Error::new(
+            err.kind(),
+            format!(
+                "the current directory is not available (was it deleted?): {}",
+                err
+            ),
+        )
+    })
+}
+
+/// Makes a path absolute and removes its `.` and `..` components, without touching the
+/// file system (symlinks are not resolved).
+pub(crate) fn absolute(path: &Path) -> io::Result<PathBuf> {
+    let mut result = if path.is_absolute() {
+        PathBuf::new()
+    } else {
+        current_dir()?
+    };
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                result.pop();
+            }
+            component => result.push(component),
+        }
+    }
+
+    Ok(result)
+}
+
+/// Returns the path relative to the given absolute base, or the absolute path if it's
+/// not under the base.
+pub(crate) fn relative_to(path: &Path, base: &Path) -> io::Result<PathBuf> {
+    let path = absolute(path)?;
+
+    Ok(match path.strip_prefix(base) {
+        Ok(relative) => relative.to_path_buf(),
+        Err(_) => path,
+    })
+}
+
+/// Like `relative_to`, but resolves symlinks first, so that a file whose target is
+/// outside of the base is displayed with its absolute target path.
+pub(crate) fn resolved_relative_to(path: &Path, resolved_base: &Path) -> io::Result<PathBuf> {
+    let path = fs::canonicalize(path).or_else(|_| absolute(path))?;
+
+    Ok(match path.strip_prefix(resolved_base) {
+        Ok(relative) => relative.to_path_buf(),
+        Err(_) => path,
+    })
+}
@@ -0,0 +1,247 @@
+use crate::crc::crc32;
+use crate::porcelain;
+use crate::printer::path_from_bytes;
+use crate::{Result, TailEdit};
+use std::borrow::Cow;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Version of the plan format, to be bumped on incompatible changes.
+const FORMAT_VERSION: u64 = 4;
+
+/// Number of bytes before the part of a file replaced by an edit which are covered by its
+/// checksum too, as appending replaces nothing.
+const CHECKED_LEN: u64 = 4096;
+
+/// Writes the changes which would be made, as a manifest which can be applied later.
+///
+/// The manifest starts with an `addeol-plan <version>` line, followed by a line per change:
+/// `<size> <crc32> <offset> <bytes> <path>`, with the CRC-32 and the bytes written in hex
+/// (`-` when there are no bytes), and the path quoted as in the porcelain output.
+pub(crate) struct PlanWriter {
+    out: BufWriter<File>,
+}
+
+/// A change listed in a plan.
+pub(crate) struct PlannedEdit {
+    pub path: PathBuf,
+    /// Size of the file when the plan was made
+    pub size: u64,
+    /// CRC-32 of the end of the file when the plan was made, see `checksum`
+    pub checksum: u32,
+    pub edit: TailEdit,
+}
+
+impl PlanWriter {
+    pub(crate) fn create(path: &Path) -> io::Result<PlanWriter> {
+        let mut out = BufWriter::new(File::create(path)?);
+        writeln!(out, "addeol-plan {}", FORMAT_VERSION)?;
+        Ok(PlanWriter { out })
+    }
+
+    pub(crate) fn write(&mut self, planned: &PlannedEdit) -> io::Result<()> {
+        write!(
+            self.out,
+            "{} {:08x} {} ",
+            planned.size, planned.checksum, planned.edit.offset
+        )?;
+
+        if planned.edit.bytes.is_empty() {
+            self.out.write_all(b"-")?;
+        }
+        for byte in planned.edit.bytes.iter() {
+            write!(self.out, "{:02x}", byte)?;
+        }
+
+        self.out.write_all(b" ")?;
+        porcelain::write_quoted_path(&mut self.out, &planned.path)?;
+        self.out.write_all(b"\n")
+    }
+
+    pub(crate) fn finish(mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Reads the changes listed in a plan written by `PlanWriter`.
+pub(crate) fn read(path: &Path) -> Result<Vec<PlannedEdit>> {
+    let contents =
+        fs::read(path).map_err(|err| format!("could not read plan {}: {}", path.display(), err))?;
+
+    let invalid = |msg: &str| format!("invalid plan {}: {}", path.display(), msg);
+
+    let mut lines = contents
+        .split(|&b| b == b'\n')
+        .filter(|line| !line.is_empty());
+
+    let version = lines
+        .next()
+        .and_then(|line| line.strip_prefix(b"addeol-plan "))
+        .and_then(|version| std::str::from_utf8(version).ok())
+        .and_then(|version| version.parse::<u64>().ok());
+
+    match version {
+        Some(FORMAT_VERSION) => {}
+        Some(version) => return Err(invalid(&format!("unsupported version {}", version)).into()),
+        None => return Err(invalid("missing version").into()),
+    }
+
+    lines
+        .enumerate()
+        .map(|(index, line)| {
+            parse_change(line)
+                .ok_or_else(|| invalid(&format!("malformed change on line {}", index + 2)).into())
+        })
+        .collect()
+}
+
+fn parse_change(line: &[u8]) -> Option<PlannedEdit> {
+    let mut fields = line.splitn(5, |&b| b == b' ');
+    let mut next = || std::str::from_utf8(fields.next()?).ok();
+
+    let size = next()?.parse().ok()?;
+    let checksum = u32::from_str_radix(next()?, 16).ok()?;
+    let offset = next()?.parse().ok()?;
+    let bytes = match next()? {
+        "-" => Vec::new(),
+        hex => decode_hex(hex)?,
+    };
+    let path = porcelain::unquote_path(fields.next()?)?;
+
+    if offset > size || path.is_empty() {
+        return None;
+    }
+
+    Some(PlannedEdit {
+        path: path_from_bytes(&path),
+        size,
+        checksum,
+        edit: TailEdit {
+            offset,
+            bytes: Cow::Owned(bytes),
+        },
+    })
+}
+
+/// Returns the CRC-32 of the end of the file replaced from the given offset, along with
+/// the `CHECKED_LEN` bytes before it.
+pub(crate) fn checksum(file: &mut (impl Read + Seek), offset: u64) -> io::Result<u32> {
+    let mut tail = Vec::new();
+    file.seek(SeekFrom::Start(offset.saturating_sub(CHECKED_LEN)))?;
+    file.read_to_end(&mut tail)?;
+    Ok(crc32(&tail))
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process};
+
+    #[test]
+    fn reads_back_written_plans() {
+        let path = env::temp_dir().join(format!("addeol-plan-{}.txt", process::id()));
+
+        let mut writer = PlanWriter::create(&path).unwrap();
+        writer
+            .write(&PlannedEdit {
+                path: PathBuf::from("dir/a \"quoted\" file.txt"),
+                size: 3,
+                checksum: 0,
+                edit: TailEdit::append(3, b"\r\n"),
+            })
+            .unwrap();
+        writer
+            .write(&PlannedEdit {
+                path: PathBuf::from("b.txt"),
+                size: 4,
+                checksum: u32::MAX,
+                edit: TailEdit::rewrite(b"a\nb\n".to_vec()),
+            })
+            .unwrap();
+        writer
+            .write(&PlannedEdit {
+                path: PathBuf::from("new\nline.txt"),
+                size: 2,
+                checksum: 1,
+                edit: TailEdit::replace(0, b""),
+            })
+            .unwrap();
+        writer.finish().unwrap();
+
+        let planned = read(&path);
+        fs::remove_file(&path).unwrap();
+        let planned = planned.unwrap();
+
+        assert_eq!(planned.len(), 3);
+        assert_eq!(planned[0].path, Path::new("dir/a \"quoted\" file.txt"));
+        assert_eq!(planned[0].size, 3);
+        assert_eq!(planned[0].checksum, 0);
+        assert_eq!(planned[0].edit.offset, 3);
+        assert_eq!(*planned[0].edit.bytes, *b"\r\n");
+        assert_eq!(planned[1].path, Path::new("b.txt"));
+        assert_eq!(planned[1].size, 4);
+        assert_eq!(planned[1].checksum, u32::MAX);
+        assert_eq!(planned[1].edit.offset, 0);
+        assert_eq!(*planned[1].edit.bytes, *b"a\nb\n");
+        assert_eq!(planned[2].path, Path::new("new\nline.txt"));
+        assert_eq!(planned[2].checksum, 1);
+        assert!(planned[2].edit.bytes.is_empty());
+    }
+
+    #[test]
+    fn checksums_the_bytes_before_the_offset() {
+        let checksum = |content: &[u8], offset| {
+            super::checksum(&mut io::Cursor::new(content), offset).unwrap()
+        };
+
+        assert_eq!(checksum(b"abc", 3), crc32(b"abc"));
+        assert_ne!(checksum(b"abc", 3), checksum(b"ab\n", 3));
+        assert_eq!(checksum(b"abc  \n\n", 3), crc32(b"abc  \n\n"));
+
+        let long = [b'a'; 5000];
+        assert_eq!(
+            checksum(&long, 5000),
+            crc32(&long[5000 - CHECKED_LEN as usize..])
+        );
+    }
+
+    #[test]
+    fn parses_changes() {
+        let planned = parse_change(b"3 0000000a 3 0d0a \"a b.txt\"").unwrap();
+        assert_eq!(planned.path, Path::new("a b.txt"));
+        assert_eq!((planned.size, planned.checksum), (3, 10));
+        assert_eq!(planned.edit.offset, 3);
+        assert_eq!(*planned.edit.bytes, *b"\r\n");
+
+        for line in [
+            &b"3 0 3 0a"[..],
+            b"3 0 3 0a ",
+            b"3 0 4 0a a.txt",
+            b"3 0 3 0g a.txt",
+            b"3 zz 3 0a a.txt",
+            b"3 0 3 0a \"a.txt",
+        ] {
+            assert!(parse_change(line).is_none(), "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn decodes_hex() {
+        assert_eq!(decode_hex("0d0A"), Some(vec![0x0d, 0x0a]));
+        assert_eq!(decode_hex(""), Some(Vec::new()));
+        assert_eq!(decode_hex("0"), None);
+        assert_eq!(decode_hex("zz"), None);
+    }
+}
@@ -0,0 +1,159 @@
+use crate::paths::PathSeparator;
+use crate::printer::path_bytes;
+use crate::FileResult;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Writes a result as a stable `<status> <path>` line meant to be parsed by scripts.
+pub(crate) fn write_result(
+    out: &mut impl Write,
+    result: &FileResult,
+    separator: PathSeparator,
+) -> io::Result<()> {
+    let status = result.status();
+    let path = result.path();
+
+    write!(out, "{}", status)?;
+
+    if let Some(path) = path {
+        write!(out, " ")?;
+        write_quoted_path(out, &separator.apply(path))?;
+    }
+
+    writeln!(out)
+}
+
+/// Writes a path as is when it is unambiguous, or as a C-style quoted string
+/// (like git does) when it contains whitespace, quotes or control characters.
+pub(crate) fn write_quoted_path(out: &mut impl Write, path: &Path) -> io::Result<()> {
+    let bytes = path_bytes(path);
+
+    let needs_quoting = bytes
+        .iter()
+        .any(|&b| b.is_ascii_whitespace() || b.is_ascii_control() || b == b'"' || b == b'\\');
+
+    if !needs_quoting {
+        return out.write_all(&bytes);
+    }
+
+    out.write_all(b"\"")?;
+
+    for &byte in bytes.iter() {
+        match byte {
+            b'"' => out.write_all(b"\\\"")?,
+            b'\\' => out.write_all(b"\\\\")?,
+            b'\t' => out.write_all(b"\\t")?,
+            b'\n' => out.write_all(b"\\n")?,
+            b'\r' => out.write_all(b"\\r")?,
+            b' ' => out.write_all(b" ")?,
+            _ if byte.is_ascii_control() || byte.is_ascii_whitespace() => {
+                write!(out, "\\{:03o}", byte)?
+            }
+            _ => out.write_all(&[byte])?,
+        }
+    }
+
+    out.write_all(b"\"")
+}
+
+/// Reads back a path written by `write_quoted_path`, returning its bytes, or `None` when
+/// the quoting is malformed.
+pub(crate) fn unquote_path(text: &[u8]) -> Option<Vec<u8>> {
+    let Some(quoted) = text.strip_prefix(b"\"") else {
+        return Some(text.to_vec());
+    };
+    let quoted = quoted.strip_suffix(b"\"")?;
+
+    let mut bytes = Vec::with_capacity(quoted.len());
+    let mut iter = quoted.iter().copied();
+
+    while let Some(byte) = iter.next() {
+        if byte != b'\\' {
+            bytes.push(byte);
+            continue;
+        }
+
+        bytes.push(match iter.next()? {
+            b't' => b'\t',
+            b'n' => b'\n',
+            b'r' => b'\r',
+            escaped @ (b'"' | b'\\') => escaped,
+            first @ b'0'..=b'3' => {
+                let mut value = first - b'0';
+                for _ in 0..2 {
+                    match iter.next()? {
+                        digit @ b'0'..=b'7' => value = value * 8 + (digit - b'0'),
+                        _ => return None,
+                    }
+                }
+                value
+            }
+            _ => return None,
+        });
+    }
+
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{unquote_path, write_quoted_path};
+    use std::path::Path;
+
+    fn quoted(path: &str) -> String {
+        let mut out = Vec::new();
+        write_quoted_path(&mut out, Path::new(path)).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn keeps_plain_paths() {
+        assert_eq!(quoted("src/main.rs"), "src/main.rs");
+    }
+
+    #[test]
+    fn quotes_spaces() {
+        assert_eq!(quoted("my file.txt"), r#""my file.txt""#);
+    }
+
+    #[test]
+    fn escapes_tabs_and_newlines() {
+        assert_eq!(quoted("a\tb.txt"), r#""a\tb.txt""#);
+        assert_eq!(quoted("a\nb.txt"), r#""a\nb.txt""#);
+        assert_eq!(quoted("a\rb.txt"), r#""a\rb.txt""#);
+    }
+
+    #[test]
+    fn escapes_quotes_and_backslashes() {
+        assert_eq!(quoted(r#"a"b.txt"#), r#""a\"b.txt""#);
+        assert_eq!(quoted(r"a\b.txt"), r#""a\\b.txt""#);
+    }
+
+    #[test]
+    fn escapes_other_control_characters_in_octal() {
+        assert_eq!(quoted("a\x1bb.txt"), r#""a\033b.txt""#);
+    }
+
+    #[test]
+    fn reads_back_quoted_paths() {
+        for path in [
+            "src/main.rs",
+            "my file.txt",
+            "a\t\n\rb\"c\\d\x1b\x7f.txt",
+            "é.txt",
+        ] {
+            assert_eq!(
+                unquote_path(quoted(path).as_bytes()).as_deref(),
+                Some(path.as_bytes())
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_quoting() {
+        assert_eq!(unquote_path(br#""a.txt"#), None);
+        assert_eq!(unquote_path(br#""a\"#), None);
+        assert_eq!(unquote_path(br#""a\x.txt""#), None);
+        assert_eq!(unquote_path(br#""a\08.txt""#), None);
+    }
+}
@@ -1,46 +1,231 @@
+use crate::paths::{self, PathSeparator};
+use crate::theme::{Palette, Theme};
 use crate::FileResult;
-use ignore::DirEntry;
-use std::io::Write;
-use std::{fmt, io};
-use termcolor::{Color, ColorSpec, StandardStream, WriteColor};
+use clap::ValueEnum;
+use std::borrow::Cow;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::{fmt, fs, io};
+use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
+
+/// Width of the right-aligned header preceding file paths.
+const HEADER_WIDTH: usize = 10;
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HeaderStyle {
+    /// Right-aligned words, such as "updated"
+    Words,
+    /// Compact symbols: + for updates, = for up to date files, - for skipped files,
+    /// ~ for warnings and ! for errors
+    Symbols,
+    /// No header at all
+    None,
+}
 
 pub struct Printer {
-    stdout: StandardStream,
+    out: Box<dyn WriteColor + Send>,
+    header_style: HeaderStyle,
+    palette: Palette,
+    path_separator: PathSeparator,
+    root: Option<PathBuf>,
+    /// Root with its symlinks resolved, when the paths are resolved too
+    resolved_root: Option<PathBuf>,
+    absolute_paths: bool,
+    canonical_paths: bool,
+    max_path_width: Option<usize>,
+    thousands_separator: String,
+    started: bool,
 }
 
 impl Printer {
-    pub(crate) fn new() -> Printer {
+    /// Creates a printer writing to stdout.
+    pub(crate) fn new(color: ColorChoice) -> Printer {
+        Printer::with_output(StandardStream::stdout(resolve_color(
+            color,
+            io::stdout().is_terminal(),
+        )))
+    }
+
+    /// Creates a printer writing to stderr.
+    pub(crate) fn stderr(color: ColorChoice) -> Printer {
+        Printer::with_output(StandardStream::stderr(resolve_color(
+            color,
+            io::stderr().is_terminal(),
+        )))
+    }
+
+    /// Creates a printer writing to the given stream, such as an in-memory buffer.
+    pub(crate) fn with_output(out: impl WriteColor + Send + 'static) -> Printer {
         Printer {
-            stdout: StandardStream::stdout(termcolor::ColorChoice::Auto),
+            out: Box::new(out),
+            header_style: HeaderStyle::Words,
+            palette: Theme::Dark.palette(),
+            path_separator: PathSeparator::Native,
+            root: None,
+            resolved_root: None,
+            absolute_paths: false,
+            canonical_paths: false,
+            max_path_width: None,
+            thousands_separator: String::new(),
+            started: false,
         }
     }
 
+    /// Displays file paths relative to the given absolute directory.
+    pub(crate) fn with_root(mut self, root: Option<PathBuf>) -> Printer {
+        self.root = root;
+        self
+    }
+
+    /// Resolves the symlinks of file paths before making them relative to the root,
+    /// so that files outside of it are displayed with their absolute path.
+    pub(crate) fn with_resolved_paths(mut self, resolved: bool) -> Printer {
+        self.resolved_root = match self.root {
+            Some(ref root) if resolved => {
+                Some(fs::canonicalize(root).unwrap_or_else(|_| root.clone()))
+            }
+            _ => None,
+        };
+        self
+    }
+
+    /// Displays absolute file paths when there's no root.
+    pub(crate) fn with_absolute_paths(mut self, absolute_paths: bool) -> Printer {
+        self.absolute_paths = absolute_paths;
+        self
+    }
+
+    /// Displays canonical file paths, or the paths as given when they can't be resolved.
+    pub(crate) fn with_canonical_paths(mut self, canonical_paths: bool) -> Printer {
+        self.canonical_paths = canonical_paths;
+        self
+    }
+
+    pub(crate) fn with_header_style(mut self, header_style: HeaderStyle) -> Printer {
+        self.header_style = header_style;
+        self
+    }
+
+    pub(crate) fn with_theme(mut self, theme: Theme) -> Printer {
+        self.palette = theme.palette();
+        self
+    }
+
+    pub(crate) fn with_path_separator(mut self, path_separator: PathSeparator) -> Printer {
+        self.path_separator = path_separator;
+        self
+    }
+
+    /// Shortens file paths so that result lines fit in the given width.
+    pub(crate) fn with_max_path_width(mut self, width: Option<usize>) -> Printer {
+        self.max_path_width = width;
+        self
+    }
+
+    pub(crate) fn with_thousands_separator(mut self, separator: String) -> Printer {
+        self.thousands_separator = separator;
+        self
+    }
+
     pub(crate) fn write_file_result(
         &mut self,
         result: &FileResult,
         dry_run: bool,
     ) -> io::Result<()> {
+        self.start()?;
+
         match result {
-            FileResult::UpdatedFile(ref entry) => {
-                self.write_header(if dry_run { "to update" } else { "updated" }, Color::Green)?;
-                self.write_file_path(entry)?;
+            FileResult::UpdatedFile(ref entry, _) => {
+                self.write_header(
+                    if dry_run {
+                        Header::ToUpdate
+                    } else {
+                        Header::Updated
+                    },
+                    self.palette.updated,
+                )?;
+                self.write_file_path(entry.path())?;
+            }
+            FileResult::UpdatedInPlace(ref entry, _) => {
+                self.write_header(
+                    if dry_run {
+                        Header::ToUpdate
+                    } else {
+                        Header::InPlace
+                    },
+                    self.palette.in_place,
+                )?;
+                self.write_file_path(entry.path())?;
             }
-            FileResult::UpToDateFile(ref entry) => {
-                self.write_header("up to date", Color::White)?;
-                self.write_file_path(entry)?;
+            FileResult::UpToDateFile(ref entry, _) => {
+                self.write_header(Header::UpToDate, self.palette.up_to_date)?;
+                self.write_file_path(entry.path())?;
+            }
+            FileResult::SkippedTooLarge(ref entry) => {
+                self.write_header(Header::TooLarge, self.palette.skipped)?;
+                self.write_file_path(entry.path())?;
+            }
+            FileResult::SkippedTooSmall(ref entry) => {
+                self.write_header(Header::TooSmall, self.palette.skipped)?;
+                self.write_file_path(entry.path())?;
+            }
+            FileResult::SkippedSymlink(ref entry) => {
+                self.write_header(Header::Symlink, self.palette.skipped)?;
+                self.write_file_path(entry.path())?;
+            }
+            FileResult::SkippedBinary(ref entry) => {
+                self.write_header(Header::Binary, self.palette.skipped)?;
+                self.write_file_path(entry.path())?;
+            }
+            FileResult::SkippedResumed(ref entry) => {
+                self.write_header(Header::Resumed, self.palette.skipped)?;
+                self.write_file_path(entry.path())?;
+            }
+            FileResult::LongFinalLine(ref path, length) => {
+                self.write_header(Header::LongLine, self.palette.warning)?;
+                self.write_file_path(path)?;
+                self.out.set_color(&ColorSpec::new())?;
+                write!(&mut self.out, " ({} bytes)", length)?;
             }
             FileResult::FileError(ref entry, ref err) => {
-                self.write_header("error", Color::Red)?;
-                self.write_file_path(entry)?;
+                self.write_header(Header::Error, self.palette.error)?;
+                self.write_file_path(entry.path())?;
+
+                self.out
+                    .set_color(ColorSpec::new().set_fg(self.palette.error))?;
+                write!(&mut self.out, " {}", err)?;
+            }
+            FileResult::Panicked(ref entry, ref err) => {
+                self.write_header(Header::Panic, self.palette.error)?;
+                self.write_file_path(entry.path())?;
+
+                self.out
+                    .set_color(ColorSpec::new().set_fg(self.palette.error))?;
+                write!(&mut self.out, " {}", err)?;
+            }
+            FileResult::UnreadableDirectory(ref path, ref err) => {
+                self.write_header(Header::Unreadable, self.palette.error)?;
+                self.write_file_path(path)?;
+
+                self.out
+                    .set_color(ColorSpec::new().set_fg(self.palette.error))?;
+                write!(&mut self.out, " {}", err)?;
+            }
+            FileResult::WalkError(Some(ref path), ref err) => {
+                self.write_header(Header::Error, self.palette.error)?;
+                self.write_file_path(path)?;
 
-                self.stdout
-                    .set_color(ColorSpec::new().set_fg(Some(Color::Red)))?;
-                write!(&mut self.stdout, "{}", err)?;
+                self.out
+                    .set_color(ColorSpec::new().set_fg(self.palette.error))?;
+                write!(&mut self.out, " {}", err)?;
             }
-            FileResult::UnknownError(ref err) => {
-                self.stdout
-                    .set_color(ColorSpec::new().set_fg(Some(Color::Red)).set_intense(true))?;
-                write!(&mut self.stdout, "{}", err)?;
+            FileResult::WalkError(None, ref err) => {
+                self.out.set_color(
+                    ColorSpec::new()
+                        .set_fg(self.palette.error)
+                        .set_intense(true),
+                )?;
+                write!(&mut self.out, "{}", err)?;
             }
         }
 
@@ -48,50 +233,406 @@ impl Printer {
         Ok(())
     }
 
-    fn write_header(&mut self, header: &str, color: Color) -> io::Result<()> {
-        self.stdout
-            .set_color(ColorSpec::new().set_fg(Some(color)))?;
-        write!(&mut self.stdout, "{:>10}", header)?;
-        self.stdout.set_color(&ColorSpec::new())?;
-        write!(&mut self.stdout, ": ")?;
+    /// Writes the name of a file which isn't found by the walker, such as stdin, and
+    /// which was updated or would be.
+    pub(crate) fn write_updated_path(&mut self, name: &Path, dry_run: bool) -> io::Result<()> {
+        self.start()?;
+        self.write_header(
+            if dry_run {
+                Header::ToUpdate
+            } else {
+                Header::Updated
+            },
+            self.palette.updated,
+        )?;
+        self.write_file_path(name)?;
+        self.out.reset()?;
+        self.writeln()
+    }
+
+    /// Writes the name of a file left untouched as it changed since a plan was made.
+    pub(crate) fn write_diverged_path(&mut self, name: &Path) -> io::Result<()> {
+        self.start()?;
+        self.write_header(Header::Diverged, self.palette.skipped)?;
+        self.write_file_path(name)?;
+        self.out.reset()?;
+        self.writeln()
+    }
+
+    /// Writes the blank line which precedes any output.
+    fn start(&mut self) -> io::Result<()> {
+        if !self.started {
+            self.started = true;
+            self.writeln()?;
+        }
         Ok(())
     }
 
-    fn write_file_path(&mut self, entry: &DirEntry) -> io::Result<()> {
-        self.stdout
-            .set_color(ColorSpec::new().set_fg(Some(Color::Cyan)))?;
-        write!(&mut self.stdout, "{}", entry.path().display())?;
+    fn write_header(&mut self, header: Header, color: Option<Color>) -> io::Result<()> {
+        if self.header_style == HeaderStyle::None {
+            return Ok(());
+        }
+
+        self.out.set_color(ColorSpec::new().set_fg(color))?;
+        if self.header_style == HeaderStyle::Symbols {
+            write!(&mut self.out, "{}", header.symbol())?;
+            self.out.set_color(&ColorSpec::new())?;
+            write!(&mut self.out, " ")?;
+        } else {
+            write!(
+                &mut self.out,
+                "{:>width$}",
+                header.word(),
+                width = HEADER_WIDTH
+            )?;
+            self.out.set_color(&ColorSpec::new())?;
+            write!(&mut self.out, ": ")?;
+        }
+        Ok(())
+    }
+
+    /// Returns the width taken by headers, including their separator.
+    fn header_width(&self) -> usize {
+        match self.header_style {
+            HeaderStyle::Words => HEADER_WIDTH + 2,
+            HeaderStyle::Symbols => 2,
+            HeaderStyle::None => 0,
+        }
+    }
+
+    fn write_file_path(&mut self, path: &Path) -> io::Result<()> {
+        self.out
+            .set_color(ColorSpec::new().set_fg(self.palette.path))?;
+
+        let path = match (&self.root, &self.resolved_root) {
+            _ if self.canonical_paths => {
+                Cow::Owned(fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()))
+            }
+            (_, Some(resolved_root)) => {
+                Cow::Owned(paths::resolved_relative_to(path, resolved_root)?)
+            }
+            (Some(root), None) => Cow::Owned(paths::relative_to(path, root)?),
+            (None, None) if self.absolute_paths => Cow::Owned(paths::absolute(path)?),
+            (None, None) => Cow::Borrowed(path),
+        };
+
+        let path = self.path_separator.apply(&path);
+        let text = path.to_string_lossy();
+        match self.max_path_width {
+            Some(width) => {
+                let width = width.saturating_sub(self.header_width());
+                write!(&mut self.out, "{}", truncate_middle(&text, width))?
+            }
+            None => write!(&mut self.out, "{}", text)?,
+        }
+        Ok(())
+    }
+
+    /// Writes a blank line separating the summary from the file results, if any.
+    pub fn write_separator(&mut self) -> io::Result<()> {
+        if self.started {
+            self.writeln()?;
+        }
         Ok(())
     }
 
     pub fn writeln(&mut self) -> io::Result<()> {
-        writeln!(&mut self.stdout)?;
+        writeln!(&mut self.out)?;
         Ok(())
     }
 
     pub fn write_stat(&mut self, label: &str, stat: fmt::Arguments) -> io::Result<()> {
-        self.stdout
-            .set_color(ColorSpec::new().set_fg(Some(Color::Yellow)))?;
-        write!(&mut self.stdout, "{:>20}", label)?;
-        self.stdout.set_color(&ColorSpec::new())?;
-        writeln!(&mut self.stdout, ": {}", stat)?;
+        self.start()?;
+
+        self.out
+            .set_color(ColorSpec::new().set_fg(self.palette.label))?;
+        write!(&mut self.out, "{:>20}", label)?;
+        self.out.set_color(&ColorSpec::new())?;
+        writeln!(&mut self.out, ": {}", stat)?;
         Ok(())
     }
+
+    pub fn write_count(&mut self, label: &str, count: usize) -> io::Result<()> {
+        let grouped = self.group_digits(count);
+        self.write_stat(label, format_args!("{}", grouped))
+    }
+
+    /// Writes the whole summary on a single line, preceded by the program name.
+    pub fn write_oneline_summary(&mut self, summary: fmt::Arguments) -> io::Result<()> {
+        self.out
+            .set_color(ColorSpec::new().set_fg(self.palette.label))?;
+        write!(&mut self.out, "addeol")?;
+        self.out.set_color(&ColorSpec::new())?;
+        writeln!(&mut self.out, ": {}", summary)
+    }
+
+    /// Formats a number with the thousands separator.
+    pub fn group_digits(&self, count: usize) -> String {
+        let digits = count.to_string();
+        let mut grouped = String::with_capacity(digits.len() * 2);
+
+        for (index, digit) in digits.chars().enumerate() {
+            if index != 0 && (digits.len() - index).is_multiple_of(3) {
+                grouped.push_str(&self.thousands_separator);
+            }
+            grouped.push(digit);
+        }
+
+        grouped
+    }
 }
 
 impl Write for Printer {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.stdout.write(buf)
+        self.out.write(buf)
     }
 
     fn flush(&mut self) -> io::Result<()> {
-        self.stdout.flush()
+        self.out.flush()
     }
 }
 
 impl Drop for Printer {
     fn drop(&mut self) {
-        let _ = self.stdout.reset();
-        let _ = self.stdout.flush();
+        let _ = self.out.reset();
+        let _ = self.out.flush();
+    }
+}
+
+/// Disables automatic colors when not writing to a terminal.
+fn resolve_color(color: ColorChoice, is_terminal: bool) -> ColorChoice {
+    match color {
+        ColorChoice::Auto if !is_terminal => ColorChoice::Never,
+        color => color,
+    }
+}
+
+pub(crate) fn write_path0(out: &mut impl Write, path: &Path) -> io::Result<()> {
+    let bytes = path_bytes(path);
+
+    // Paths can't contain NUL on supported platforms, but never let one split an entry
+    if bytes.contains(&0) {
+        for &byte in bytes.iter() {
+            match byte {
+                0 => out.write_all(b"\\0")?,
+                b'\\' => out.write_all(b"\\\\")?,
+                _ => out.write_all(&[byte])?,
+            }
+        }
+    } else {
+        out.write_all(&bytes)?;
+    }
+
+    out.write_all(b"\0")
+}
+
+pub(crate) fn path_bytes(path: &Path) -> Cow<'_, [u8]> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        Cow::Borrowed(path.as_os_str().as_bytes())
+    }
+    #[cfg(not(unix))]
+    {
+        match path.to_string_lossy() {
+            Cow::Borrowed(path) => Cow::Borrowed(path.as_bytes()),
+            Cow::Owned(path) => Cow::Owned(path.into_bytes()),
+        }
+    }
+}
+
+/// Converts bytes returned by `path_bytes` or read from a command back to a path.
+pub(crate) fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    #[cfg(unix)]
+    {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+        Path::new(OsStr::from_bytes(bytes)).to_path_buf()
+    }
+    #[cfg(not(unix))]
+    {
+        Path::new(&*String::from_utf8_lossy(bytes)).to_path_buf()
+    }
+}
+
+/// Label preceding a file path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Header {
+    Updated,
+    ToUpdate,
+    InPlace,
+    UpToDate,
+    TooLarge,
+    TooSmall,
+    Symlink,
+    Binary,
+    Resumed,
+    Diverged,
+    LongLine,
+    Error,
+    Panic,
+    Unreadable,
+}
+
+impl Header {
+    fn word(self) -> &'static str {
+        match self {
+            Header::Updated => "updated",
+            Header::ToUpdate => "to update",
+            Header::InPlace => "in place",
+            Header::UpToDate => "up to date",
+            Header::TooLarge => "too large",
+            Header::TooSmall => "too small",
+            Header::Symlink => "symlink",
+            Header::Binary => "binary",
+            Header::Resumed => "resumed",
+            Header::Diverged => "diverged",
+            Header::LongLine => "long line",
+            Header::Error => "error",
+            Header::Panic => "panic",
+            Header::Unreadable => "unreadable",
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            Header::Updated | Header::ToUpdate | Header::InPlace => "+",
+            Header::UpToDate => "=",
+            Header::TooLarge
+            | Header::TooSmall
+            | Header::Symlink
+            | Header::Binary
+            | Header::Resumed
+            | Header::Diverged => "-",
+            Header::LongLine => "~",
+            Header::Error | Header::Panic | Header::Unreadable => "!",
+        }
+    }
+}
+
+/// Replaces the middle of the text with an ellipsis if it's longer than the given width.
+fn truncate_middle(text: &str, width: usize) -> Cow<'_, str> {
+    let len = text.chars().count();
+    if len <= width || width < 3 {
+        return Cow::Borrowed(text);
+    }
+
+    let tail = (width - 1) / 2;
+    let head = width - 1 - tail;
+
+    let mut result: String = text.chars().take(head).collect();
+    result.push('…');
+    result.extend(text.chars().skip(len - tail));
+    Cow::Owned(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use termcolor::Buffer;
+
+    /// In-memory output which can still be read once given to a printer.
+    #[derive(Clone)]
+    struct SharedBuffer(Arc<Mutex<Buffer>>);
+
+    impl SharedBuffer {
+        fn new() -> SharedBuffer {
+            SharedBuffer(Arc::new(Mutex::new(Buffer::no_color())))
+        }
+
+        fn text(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().as_slice().to_vec()).unwrap()
+        }
+    }
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.0.lock().unwrap().flush()
+        }
+    }
+
+    impl WriteColor for SharedBuffer {
+        fn supports_color(&self) -> bool {
+            self.0.lock().unwrap().supports_color()
+        }
+
+        fn set_color(&mut self, spec: &ColorSpec) -> io::Result<()> {
+            self.0.lock().unwrap().set_color(spec)
+        }
+
+        fn reset(&mut self) -> io::Result<()> {
+            self.0.lock().unwrap().reset()
+        }
+    }
+
+    #[test]
+    fn writes_paths_and_stats() {
+        let out = SharedBuffer::new();
+        let mut printer =
+            Printer::with_output(out.clone()).with_thousands_separator(",".to_string());
+
+        printer
+            .write_updated_path(Path::new("a.txt"), false)
+            .unwrap();
+        printer.write_diverged_path(Path::new("b.txt")).unwrap();
+        printer.write_separator().unwrap();
+        printer.write_count("total files", 1234).unwrap();
+
+        assert_eq!(
+            out.text(),
+            "\n   updated: a.txt\n  diverged: b.txt\n\n         total files: 1,234\n"
+        );
+    }
+
+    #[test]
+    fn writes_symbol_headers() {
+        let out = SharedBuffer::new();
+        let mut printer = Printer::with_output(out.clone()).with_header_style(HeaderStyle::Symbols);
+
+        printer
+            .write_updated_path(Path::new("a.txt"), true)
+            .unwrap();
+        printer.write_diverged_path(Path::new("b.txt")).unwrap();
+
+        assert_eq!(out.text(), "\n+ a.txt\n- b.txt\n");
+    }
+
+    #[test]
+    fn writes_nothing_before_stats_without_results() {
+        let out = SharedBuffer::new();
+        let mut printer = Printer::with_output(out.clone());
+
+        printer.write_separator().unwrap();
+        printer.write_oneline_summary(format_args!("done")).unwrap();
+
+        assert_eq!(out.text(), "addeol: done\n");
+    }
+
+    fn path0(path: &Path) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_path0(&mut out, path).unwrap();
+        out
+    }
+
+    #[test]
+    fn writes_paths_as_is_followed_by_nul() {
+        assert_eq!(path0(Path::new("my file.txt")), b"my file.txt\0");
+        assert_eq!(path0(Path::new("a\tb.txt")), b"a\tb.txt\0");
+        assert_eq!(path0(Path::new("a\nb.txt")), b"a\nb.txt\0");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn escapes_nul_in_paths() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = Path::new(OsStr::from_bytes(b"a\0b\\c"));
+        assert_eq!(path0(path), b"a\\0b\\\\c\0");
     }
 }
@@ -0,0 +1,121 @@
+use crate::report::Report;
+use clap::ValueEnum;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Minimum delay between two redraws of the progress line.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+const SPINNER: &[char] = &['|', '/', '-', '\\'];
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ProgressMode {
+    /// Show the number of files processed so far
+    Spinner,
+    /// Count the files first, then show a percentage and an estimated time left
+    Precount,
+}
+
+/// Progress line drawn on stderr, which is cleared before anything else is printed.
+pub(crate) struct Bar {
+    total: Option<usize>,
+    done: usize,
+    start: Instant,
+    last_draw: Option<Instant>,
+    drawn_len: usize,
+}
+
+impl Bar {
+    /// Creates a progress line, with a percentage when the total number of files is known.
+    pub(crate) fn new(total: Option<usize>) -> Self {
+        Bar {
+            total,
+            done: 0,
+            start: Instant::now(),
+            last_draw: None,
+            drawn_len: 0,
+        }
+    }
+
+    /// Counts a processed file, and redraws the line if it's time to.
+    pub(crate) fn tick(&mut self) {
+        self.done += 1;
+
+        if self
+            .last_draw
+            .is_some_and(|last| last.elapsed() < REDRAW_INTERVAL)
+        {
+            return;
+        }
+
+        let elapsed = self.start.elapsed();
+        let line = match self.total {
+            Some(total) => {
+                let total = total.max(self.done);
+                let left = elapsed.as_secs_f64() / self.done as f64 * (total - self.done) as f64;
+                format!(
+                    "{}/{} files ({}%), {:.0}s left",
+                    self.done,
+                    total,
+                    self.done * 100 / total,
+                    left
+                )
+            }
+            None => format!(
+                "{} {} files ({:.1}s)",
+                SPINNER[self.done % SPINNER.len()],
+                self.done,
+                elapsed.as_secs_f64()
+            ),
+        };
+
+        self.clear();
+        eprint!("{}", line);
+        self.drawn_len = line.chars().count();
+        self.last_draw = Some(Instant::now());
+    }
+
+    /// Erases the progress line, if it was drawn.
+    pub(crate) fn clear(&mut self) {
+        if self.drawn_len != 0 {
+            eprint!("\r{:1$}\r", "", self.drawn_len);
+            self.drawn_len = 0;
+        }
+    }
+}
+
+/// Makes SIGUSR1 request a progress snapshot, on Unix.
+#[cfg(unix)]
+pub(crate) fn install_handler() {
+    extern "C" fn handler(_: libc::c_int) {
+        REQUESTED.store(true, Ordering::Relaxed);
+    }
+
+    unsafe {
+        libc::signal(libc::SIGUSR1, handler as *const () as libc::sighandler_t);
+    }
+}
+
+#[cfg(not(unix))]
+pub(crate) fn install_handler() {}
+
+/// Prints a progress snapshot to stderr if one was requested since the last call.
+pub(crate) fn report_if_requested(report: &Report, start: Instant) {
+    if !REQUESTED.swap(false, Ordering::Relaxed) {
+        return;
+    }
+
+    let elapsed = start.elapsed().as_secs_f64();
+    let rate = if elapsed > 0.0 {
+        report.file_count as f64 / elapsed
+    } else {
+        0.0
+    };
+
+    eprintln!(
+        "progress: {} files processed, {} updated, {} errors in {:.1}s ({:.0} files/s)",
+        report.file_count, report.updated_count, report.error_count, elapsed, rate
+    );
+}
@@ -0,0 +1,136 @@
+use crate::{eol, Change, FileResult};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Aggregate counts of a run, built from the individual file results.
+#[derive(Debug, Default)]
+pub struct Report {
+    /// Outcome of each file, only kept by `run()`: the command line prints them as they come
+    pub outcomes: Vec<FileOutcome>,
+    pub file_count: usize,
+    pub updated_count: usize,
+    pub in_place_count: usize,
+    pub lf_count: usize,
+    pub crlf_count: usize,
+    pub bytes_removed: u64,
+    pub bytes_added: u64,
+    pub too_large_count: usize,
+    pub too_small_count: usize,
+    pub symlink_count: usize,
+    pub binary_count: usize,
+    pub resumed_count: usize,
+    /// Archive entries which are compressed or encrypted
+    pub compressed_count: usize,
+    pub long_line_count: usize,
+    pub unreadable_dir_count: usize,
+    pub panic_count: usize,
+    /// Files listed in a plan which changed since it was made
+    pub diverged_count: usize,
+    pub error_count: usize,
+    /// Number of errors by message
+    pub error_messages: BTreeMap<String, usize>,
+    /// Whether the search stopped because of --max-matches
+    pub max_matches_reached: bool,
+    pub elapsed: Duration,
+}
+
+/// Outcome of a single file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileOutcome {
+    /// Path of the file as walked, when known
+    pub path: Option<PathBuf>,
+    /// Short name of the outcome, as in the machine-readable formats
+    pub status: &'static str,
+    /// Bytes removed and added, for the updated files
+    pub change: Option<(u64, u64)>,
+    pub error: Option<String>,
+}
+
+impl FileOutcome {
+    pub(crate) fn new(result: &FileResult) -> FileOutcome {
+        FileOutcome {
+            path: result.path().map(PathBuf::from),
+            status: result.status(),
+            change: result.change().map(|change| (change.removed, change.added)),
+            error: result.error().map(|err| err.to_string()),
+        }
+    }
+}
+
+impl Report {
+    pub(crate) fn record(&mut self, result: &FileResult) {
+        if let Some(err) = result.error() {
+            *self.error_messages.entry(err.to_string()).or_default() += 1;
+        }
+
+        match result {
+            FileResult::UpdatedFile(_, change) => self.record_update(change),
+            FileResult::UpdatedInPlace(_, change) => {
+                self.file_count += 1;
+                self.updated_count += 1;
+                self.in_place_count += 1;
+                self.record_change(change);
+            }
+            FileResult::UpToDateFile(_, _) => {
+                self.file_count += 1;
+            }
+            FileResult::SkippedTooLarge(_) => {
+                self.file_count += 1;
+                self.too_large_count += 1;
+            }
+            FileResult::SkippedTooSmall(_) => {
+                self.file_count += 1;
+                self.too_small_count += 1;
+            }
+            FileResult::SkippedBinary(_) => {
+                self.file_count += 1;
+                self.binary_count += 1;
+            }
+            FileResult::SkippedResumed(_) => {
+                self.file_count += 1;
+                self.resumed_count += 1;
+            }
+            FileResult::LongFinalLine(_, _) => {
+                self.long_line_count += 1;
+            }
+            FileResult::SkippedSymlink(_) => {
+                self.symlink_count += 1;
+            }
+            FileResult::FileError(_, _) => {
+                self.file_count += 1;
+                self.error_count += 1;
+            }
+            FileResult::Panicked(_, _) => {
+                self.file_count += 1;
+                self.panic_count += 1;
+                self.error_count += 1;
+            }
+            FileResult::UnreadableDirectory(_, _) => {
+                self.unreadable_dir_count += 1;
+                self.error_count += 1;
+            }
+            FileResult::WalkError(_, _) => {
+                self.error_count += 1;
+            }
+        }
+    }
+
+    /// Records a file which was updated, or would be in dry-run mode.
+    pub(crate) fn record_update(&mut self, change: &Change) {
+        self.file_count += 1;
+        self.updated_count += 1;
+        self.record_change(change);
+    }
+
+    fn record_change(&mut self, change: &Change) {
+        self.bytes_removed += change.removed;
+        self.bytes_added += change.added;
+
+        if change.eol == eol::CRLF {
+            self.crlf_count += 1;
+        } else {
+            self.lf_count += 1;
+        }
+    }
+}
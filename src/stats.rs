@@ -0,0 +1,22 @@
+use crate::report::Report;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Version of the JSON stats schema, to be bumped on incompatible changes.
+const SCHEMA_VERSION: u32 = 1;
+
+pub(crate) fn write_json(path: &Path, report: &Report) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+
+    writeln!(out, "{{")?;
+    writeln!(out, "  \"version\": {},", SCHEMA_VERSION)?;
+    writeln!(out, "  \"total_files\": {},", report.file_count)?;
+    writeln!(out, "  \"updated_files\": {},", report.updated_count)?;
+    writeln!(out, "  \"errors\": {},", report.error_count)?;
+    writeln!(out, "  \"skipped_symlinks\": {},", report.symlink_count)?;
+    writeln!(out, "  \"elapsed_ms\": {}", report.elapsed.as_millis())?;
+    writeln!(out, "}}")?;
+
+    out.flush()
+}
@@ -0,0 +1,139 @@
+use crate::printer::Printer;
+use crate::report::Report;
+use std::fmt::Write;
+
+/// Template of the one-line summary, matching the default output.
+pub(crate) const DEFAULT_STAT_FORMAT: &str =
+    "{updated} {action}, {total} total, {errors} errors ({elapsed})";
+
+/// A summary line template, given with --stat-format.
+///
+/// Placeholders are written between braces, and `{{` and `}}` stand for literal braces.
+#[derive(Debug, Clone)]
+pub(crate) struct StatFormat {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone)]
+enum Part {
+    Text(String),
+    Field(Field),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Field {
+    Total,
+    Updated,
+    Errors,
+    Symlinks,
+    Elapsed,
+    Action,
+}
+
+impl Field {
+    const NAMES: &'static [(&'static str, Field)] = &[
+        ("total", Field::Total),
+        ("updated", Field::Updated),
+        ("errors", Field::Errors),
+        ("symlinks", Field::Symlinks),
+        ("elapsed", Field::Elapsed),
+        ("action", Field::Action),
+    ];
+
+    fn from_name(name: &str) -> Option<Field> {
+        Self::NAMES
+            .iter()
+            .find(|(field_name, _)| *field_name == name)
+            .map(|(_, field)| *field)
+    }
+}
+
+impl StatFormat {
+    /// Parses a template, failing on unknown placeholders and unbalanced braces.
+    pub(crate) fn parse(template: &str) -> Result<StatFormat, String> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' => {
+                    if chars.as_str().starts_with('{') {
+                        chars.next();
+                        text.push('{');
+                        continue;
+                    }
+
+                    let rest = chars.as_str();
+                    let end = rest
+                        .find('}')
+                        .ok_or_else(|| "unterminated placeholder".to_string())?;
+
+                    let name = &rest[..end];
+                    let field = Field::from_name(name).ok_or_else(|| {
+                        let known = Field::NAMES
+                            .iter()
+                            .map(|(name, _)| format!("{{{}}}", name))
+                            .collect::<Vec<_>>();
+                        format!(
+                            "unknown placeholder {{{}}} (expected one of {})",
+                            name,
+                            known.join(", ")
+                        )
+                    })?;
+
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(Part::Field(field));
+                    chars = rest[end + 1..].chars();
+                }
+                '}' => {
+                    if !chars.as_str().starts_with('}') {
+                        return Err("unmatched '}', use '}}' for a literal brace".to_string());
+                    }
+                    chars.next();
+                    text.push('}');
+                }
+                _ => text.push(c),
+            }
+        }
+
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+
+        Ok(StatFormat { parts })
+    }
+
+    /// Expands the placeholders with the counts of the report.
+    pub(crate) fn expand(&self, report: &Report, printer: &Printer, dry_run: bool) -> String {
+        let mut line = String::new();
+
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => line.push_str(text),
+                Part::Field(Field::Total) => {
+                    line.push_str(&printer.group_digits(report.file_count))
+                }
+                Part::Field(Field::Updated) => {
+                    line.push_str(&printer.group_digits(report.updated_count))
+                }
+                Part::Field(Field::Errors) => {
+                    line.push_str(&printer.group_digits(report.error_count))
+                }
+                Part::Field(Field::Symlinks) => {
+                    line.push_str(&printer.group_digits(report.symlink_count))
+                }
+                Part::Field(Field::Elapsed) => {
+                    let _ = write!(line, "{:.1}s", report.elapsed.as_secs_f64());
+                }
+                Part::Field(Field::Action) => {
+                    line.push_str(if dry_run { "to update" } else { "updated" })
+                }
+            }
+        }
+
+        line
+    }
+}
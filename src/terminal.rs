@@ -0,0 +1,28 @@
+use std::env;
+
+/// Width used when the terminal width can't be determined.
+const DEFAULT_WIDTH: usize = 80;
+
+/// Returns the width of the terminal, falling back to `$COLUMNS` and then to a default.
+pub(crate) fn width() -> usize {
+    query_width()
+        .or_else(|| env::var("COLUMNS").ok()?.parse().ok())
+        .filter(|&width| width != 0)
+        .unwrap_or(DEFAULT_WIDTH)
+}
+
+#[cfg(unix)]
+fn query_width() -> Option<usize> {
+    [libc::STDOUT_FILENO, libc::STDERR_FILENO]
+        .into_iter()
+        .find_map(|fd| {
+            let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+            let result = unsafe { libc::ioctl(fd, libc::TIOCGWINSZ, &mut size) };
+            (result == 0 && size.ws_col != 0).then_some(size.ws_col as usize)
+        })
+}
+
+#[cfg(not(unix))]
+fn query_width() -> Option<usize> {
+    None
+}
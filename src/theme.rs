@@ -0,0 +1,77 @@
+use clap::ValueEnum;
+use std::env;
+use termcolor::Color;
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Theme {
+    /// Light or dark, depending on the terminal background when it's advertised
+    Auto,
+    /// Colors for a dark background
+    Dark,
+    /// Colors for a light background
+    Light,
+    /// No colors at all
+    Mono,
+}
+
+/// Colors used by the printer, where `None` is the terminal's default.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Palette {
+    pub updated: Option<Color>,
+    pub in_place: Option<Color>,
+    pub up_to_date: Option<Color>,
+    pub skipped: Option<Color>,
+    pub warning: Option<Color>,
+    pub error: Option<Color>,
+    pub path: Option<Color>,
+    pub label: Option<Color>,
+}
+
+const DARK: Palette = Palette {
+    updated: Some(Color::Green),
+    in_place: Some(Color::Yellow),
+    up_to_date: Some(Color::White),
+    skipped: Some(Color::Magenta),
+    warning: Some(Color::Yellow),
+    error: Some(Color::Red),
+    path: Some(Color::Cyan),
+    label: Some(Color::Yellow),
+};
+
+const LIGHT: Palette = Palette {
+    updated: Some(Color::Green),
+    in_place: Some(Color::Blue),
+    up_to_date: Some(Color::Black),
+    skipped: Some(Color::Magenta),
+    warning: Some(Color::Blue),
+    error: Some(Color::Red),
+    path: Some(Color::Blue),
+    label: Some(Color::Blue),
+};
+
+const MONO: Palette = Palette {
+    updated: None,
+    in_place: None,
+    up_to_date: None,
+    skipped: None,
+    warning: None,
+    error: None,
+    path: None,
+    label: None,
+};
+
+impl Theme {
+    pub(crate) fn palette(self) -> Palette {
+        match self {
+            Theme::Auto if has_light_background() => LIGHT,
+            Theme::Auto | Theme::Dark => DARK,
+            Theme::Light => LIGHT,
+            Theme::Mono => MONO,
+        }
+    }
+}
+
+/// Reads the background color some terminals advertise in `$COLORFGBG`, such as `0;15`.
+fn has_light_background() -> bool {
+    env::var("COLORFGBG").is_ok_and(|value| matches!(value.rsplit(';').next(), Some("7" | "15")))
+}
@@ -0,0 +1,383 @@
+use crate::crc::crc32;
+use std::io::{self, Write};
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const END_SIGNATURE: u32 = 0x0605_4b50;
+
+const LOCAL_HEADER_LEN: usize = 30;
+const CENTRAL_HEADER_LEN: usize = 46;
+const END_LEN: usize = 22;
+
+/// Compression method of entries stored as they are.
+const METHOD_STORED: u16 = 0;
+
+const FLAG_ENCRYPTED: u16 = 1 << 0;
+const FLAG_DATA_DESCRIPTOR: u16 = 1 << 3;
+
+/// A zip archive read in memory, for --archive.
+///
+/// Only stored (uncompressed) entries can be read and modified, the other ones are copied
+/// as they are. Zip64 archives and archives spanning several disks aren't supported.
+pub(crate) struct Archive {
+    pub entries: Vec<Entry>,
+    comment: Vec<u8>,
+}
+
+pub(crate) struct Entry {
+    pub name: Vec<u8>,
+    version_made_by: u16,
+    version_needed: u16,
+    flags: u16,
+    method: u16,
+    time: u16,
+    date: u16,
+    crc: u32,
+    uncompressed_size: u32,
+    internal_attributes: u16,
+    external_attributes: u32,
+    local_extra: Vec<u8>,
+    central_extra: Vec<u8>,
+    comment: Vec<u8>,
+    /// Data as stored in the archive, compressed or not
+    data: Vec<u8>,
+}
+
+impl Entry {
+    pub(crate) fn is_directory(&self) -> bool {
+        self.name.ends_with(b"/")
+    }
+
+    /// Returns the content of the entry, if it's stored without compression nor encryption.
+    pub(crate) fn content(&self) -> Option<&[u8]> {
+        (self.method == METHOD_STORED && self.flags & FLAG_ENCRYPTED == 0).then_some(&self.data)
+    }
+
+    /// Replaces the content of a stored entry.
+    pub(crate) fn set_content(&mut self, content: Vec<u8>) {
+        debug_assert_eq!(self.method, METHOD_STORED);
+        self.crc = crc32(&content);
+        self.uncompressed_size = content.len() as u32;
+        self.data = content;
+    }
+}
+
+impl Archive {
+    pub(crate) fn parse(bytes: &[u8]) -> Result<Archive, String> {
+        let end = find_end(bytes).ok_or("not a zip archive")?;
+
+        let mut reader = Reader::at(bytes, end + 4);
+        let disk = reader.u16()?;
+        let directory_disk = reader.u16()?;
+        let _disk_entry_count = reader.u16()?;
+        let entry_count = reader.u16()?;
+        let directory_size = reader.u32()?;
+        let directory_offset = reader.u32()?;
+        let comment_len = reader.u16()?;
+        let comment = reader.bytes(comment_len as usize)?.to_vec();
+
+        if disk != 0 || directory_disk != 0 {
+            return Err("archives spanning several disks aren't supported".to_string());
+        }
+        if entry_count == u16::MAX || directory_size == u32::MAX || directory_offset == u32::MAX {
+            return Err("zip64 archives aren't supported".to_string());
+        }
+
+        let mut reader = Reader::at(bytes, directory_offset as usize);
+        let mut entries = Vec::with_capacity(entry_count as usize);
+
+        for _ in 0..entry_count {
+            if reader.u32()? != CENTRAL_HEADER_SIGNATURE {
+                return Err("invalid central directory".to_string());
+            }
+
+            let version_made_by = reader.u16()?;
+            let version_needed = reader.u16()?;
+            let flags = reader.u16()?;
+            let method = reader.u16()?;
+            let time = reader.u16()?;
+            let date = reader.u16()?;
+            let crc = reader.u32()?;
+            let compressed_size = reader.u32()?;
+            let uncompressed_size = reader.u32()?;
+            let name_len = reader.u16()? as usize;
+            let extra_len = reader.u16()? as usize;
+            let comment_len = reader.u16()? as usize;
+            let _start_disk = reader.u16()?;
+            let internal_attributes = reader.u16()?;
+            let external_attributes = reader.u32()?;
+            let local_offset = reader.u32()?;
+            let name = reader.bytes(name_len)?.to_vec();
+            let central_extra = reader.bytes(extra_len)?.to_vec();
+            let comment = reader.bytes(comment_len)?.to_vec();
+
+            if compressed_size == u32::MAX
+                || uncompressed_size == u32::MAX
+                || local_offset == u32::MAX
+            {
+                return Err("zip64 archives aren't supported".to_string());
+            }
+
+            let mut local = Reader::at(bytes, local_offset as usize);
+            if local.u32()? != LOCAL_HEADER_SIGNATURE {
+                return Err(format!(
+                    "invalid local header for {}",
+                    String::from_utf8_lossy(&name)
+                ));
+            }
+            local.skip(LOCAL_HEADER_LEN - 4 - 4)?;
+            let local_name_len = local.u16()? as usize;
+            let local_extra_len = local.u16()? as usize;
+            local.skip(local_name_len)?;
+            let local_extra = local.bytes(local_extra_len)?.to_vec();
+            let data = local.bytes(compressed_size as usize)?.to_vec();
+
+            entries.push(Entry {
+                name,
+                version_made_by,
+                version_needed,
+                flags,
+                method,
+                time,
+                date,
+                crc,
+                uncompressed_size,
+                internal_attributes,
+                external_attributes,
+                local_extra,
+                central_extra,
+                comment,
+                data,
+            });
+        }
+
+        Ok(Archive { entries, comment })
+    }
+
+    /// Writes the archive, with the sizes and checksums in the local headers rather than
+    /// in data descriptors.
+    ///
+    /// Fails if the archive grew over the 4 GiB zip64 would be needed for.
+    pub(crate) fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        let mut offsets = Vec::with_capacity(self.entries.len());
+        let mut offset = 0usize;
+
+        for entry in &self.entries {
+            offsets.push(to_u32(offset)?);
+
+            let mut header = Vec::with_capacity(LOCAL_HEADER_LEN);
+            put_u32(&mut header, LOCAL_HEADER_SIGNATURE);
+            put_u16(&mut header, entry.version_needed);
+            put_u16(&mut header, entry.flags & !FLAG_DATA_DESCRIPTOR);
+            put_u16(&mut header, entry.method);
+            put_u16(&mut header, entry.time);
+            put_u16(&mut header, entry.date);
+            put_u32(&mut header, entry.crc);
+            put_u32(&mut header, to_u32(entry.data.len())?);
+            put_u32(&mut header, entry.uncompressed_size);
+            put_u16(&mut header, entry.name.len() as u16);
+            put_u16(&mut header, entry.local_extra.len() as u16);
+
+            out.write_all(&header)?;
+            out.write_all(&entry.name)?;
+            out.write_all(&entry.local_extra)?;
+            out.write_all(&entry.data)?;
+
+            offset += header.len() + entry.name.len() + entry.local_extra.len() + entry.data.len();
+        }
+
+        let directory_offset = offset;
+
+        for (entry, local_offset) in self.entries.iter().zip(offsets) {
+            let mut header = Vec::with_capacity(CENTRAL_HEADER_LEN);
+            put_u32(&mut header, CENTRAL_HEADER_SIGNATURE);
+            put_u16(&mut header, entry.version_made_by);
+            put_u16(&mut header, entry.version_needed);
+            put_u16(&mut header, entry.flags & !FLAG_DATA_DESCRIPTOR);
+            put_u16(&mut header, entry.method);
+            put_u16(&mut header, entry.time);
+            put_u16(&mut header, entry.date);
+            put_u32(&mut header, entry.crc);
+            put_u32(&mut header, to_u32(entry.data.len())?);
+            put_u32(&mut header, entry.uncompressed_size);
+            put_u16(&mut header, entry.name.len() as u16);
+            put_u16(&mut header, entry.central_extra.len() as u16);
+            put_u16(&mut header, entry.comment.len() as u16);
+            put_u16(&mut header, 0);
+            put_u16(&mut header, entry.internal_attributes);
+            put_u32(&mut header, entry.external_attributes);
+            put_u32(&mut header, local_offset);
+
+            out.write_all(&header)?;
+            out.write_all(&entry.name)?;
+            out.write_all(&entry.central_extra)?;
+            out.write_all(&entry.comment)?;
+
+            offset +=
+                header.len() + entry.name.len() + entry.central_extra.len() + entry.comment.len();
+        }
+
+        let mut end = Vec::with_capacity(END_LEN);
+        put_u32(&mut end, END_SIGNATURE);
+        put_u16(&mut end, 0);
+        put_u16(&mut end, 0);
+        put_u16(&mut end, self.entries.len() as u16);
+        put_u16(&mut end, self.entries.len() as u16);
+        put_u32(&mut end, to_u32(offset - directory_offset)?);
+        put_u32(&mut end, to_u32(directory_offset)?);
+        put_u16(&mut end, self.comment.len() as u16);
+
+        out.write_all(&end)?;
+        out.write_all(&self.comment)
+    }
+}
+
+/// Looks for the end of central directory record, which is followed by a comment of up
+/// to 64 KiB.
+fn find_end(bytes: &[u8]) -> Option<usize> {
+    let last = bytes.len().checked_sub(END_LEN)?;
+    let first = last.saturating_sub(u16::MAX as usize);
+
+    (first..=last)
+        .rev()
+        .find(|&index| bytes[index..index + 4] == END_SIGNATURE.to_le_bytes())
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn at(bytes: &'a [u8], pos: usize) -> Reader<'a> {
+        Reader { bytes, pos }
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let bytes = self
+            .pos
+            .checked_add(len)
+            .and_then(|end| self.bytes.get(self.pos..end))
+            .ok_or("truncated zip archive")?;
+
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, len: usize) -> Result<(), String> {
+        self.bytes(len).map(|_| ())
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+}
+
+/// Converts a size or an offset to the 32 bits zip has room for without zip64.
+fn to_u32(value: usize) -> io::Result<u32> {
+    u32::try_from(value).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the archive would need zip64, which isn't supported",
+        )
+    })
+}
+
+fn put_u16(out: &mut Vec<u8>, value: u16) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Archive written by Python's zipfile, holding a `d/` directory, a stored `a.txt`
+    /// containing "hello", and a deflated `b.txt`, with a "note" comment.
+    const ARCHIVE: &[u8] =
+        b"\x50\x4b\x03\x04\x14\x00\x00\x00\x00\x00\x00\x00\x21\x00\x00\x00\x00\x00\x00\x00\x00\x00\
+      \x00\x00\x00\x00\x02\x00\x00\x00\x64\x2f\x50\x4b\x03\x04\x14\x00\x00\x00\x00\x00\x19\xbc\
+      \x4f\x5d\x86\xa6\x10\x36\x05\x00\x00\x00\x05\x00\x00\x00\x05\x00\x00\x00\x61\x2e\x74\x78\
+      \x74\x68\x65\x6c\x6c\x6f\x50\x4b\x03\x04\x14\x00\x00\x00\x08\x00\x19\xbc\x4f\x5d\x9f\x9d\
+      \x08\x5e\x0b\x00\x00\x00\x78\x00\x00\x00\x05\x00\x00\x00\x62\x2e\x74\x78\x74\x2b\xcf\x2f\
+      \xca\x49\x51\x28\xa7\x3b\x09\x00\x50\x4b\x01\x02\x14\x03\x14\x00\x00\x00\x00\x00\x00\x00\
+      \x21\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x02\x00\x00\x00\x00\x00\x00\x00\
+      \x00\x00\x00\x00\x80\x01\x00\x00\x00\x00\x64\x2f\x50\x4b\x01\x02\x14\x03\x14\x00\x00\x00\
+      \x00\x00\x19\xbc\x4f\x5d\x86\xa6\x10\x36\x05\x00\x00\x00\x05\x00\x00\x00\x05\x00\x00\x00\
+      \x00\x00\x00\x00\x00\x00\x00\x00\x80\x01\x20\x00\x00\x00\x61\x2e\x74\x78\x74\x50\x4b\x01\
+      \x02\x14\x03\x14\x00\x00\x00\x08\x00\x19\xbc\x4f\x5d\x9f\x9d\x08\x5e\x0b\x00\x00\x00\x78\
+      \x00\x00\x00\x05\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x80\x01\x48\x00\x00\x00\x62\
+      \x2e\x74\x78\x74\x50\x4b\x05\x06\x00\x00\x00\x00\x03\x00\x03\x00\x96\x00\x00\x00\x76\x00\
+      \x00\x00\x04\x00\x6e\x6f\x74\x65";
+
+    fn write(archive: &Archive) -> Vec<u8> {
+        let mut out = Vec::new();
+        archive.write(&mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn parses_entries() {
+        let archive = Archive::parse(ARCHIVE).unwrap();
+        let names = archive
+            .entries
+            .iter()
+            .map(|entry| entry.name.as_slice())
+            .collect::<Vec<_>>();
+
+        assert_eq!(names, [&b"d/"[..], b"a.txt", b"b.txt"]);
+        assert!(archive.entries[0].is_directory());
+        assert!(!archive.entries[1].is_directory());
+        assert_eq!(archive.entries[1].content(), Some(&b"hello"[..]));
+        assert_eq!(archive.entries[2].content(), None);
+    }
+
+    #[test]
+    fn writes_unchanged_archives_as_they_were() {
+        let archive = Archive::parse(ARCHIVE).unwrap();
+        assert_eq!(write(&archive), ARCHIVE);
+    }
+
+    #[test]
+    fn writes_modified_entries() {
+        let mut archive = Archive::parse(ARCHIVE).unwrap();
+        archive.entries[1].set_content(b"hello\n".to_vec());
+
+        let written = write(&archive);
+        let reread = Archive::parse(&written).unwrap();
+
+        let entry = &reread.entries[1];
+        assert_eq!(entry.content(), Some(&b"hello\n"[..]));
+        assert_eq!(entry.crc, crc32(b"hello\n"));
+        assert_eq!(entry.uncompressed_size, 6);
+
+        let original = Archive::parse(ARCHIVE).unwrap();
+        assert_eq!(reread.entries[2].data, original.entries[2].data);
+        assert_eq!(reread.entries[2].crc, original.entries[2].crc);
+        assert_eq!(reread.comment, b"note");
+    }
+
+    #[test]
+    fn rejects_invalid_archives() {
+        assert!(Archive::parse(b"").is_err());
+        assert!(Archive::parse(b"not a zip archive, only some text").is_err());
+        assert!(Archive::parse(&ARCHIVE[..ARCHIVE.len() - 30]).is_err());
+        assert!(Archive::parse(&ARCHIVE[100..]).is_err());
+    }
+
+    #[test]
+    fn refuses_offsets_needing_zip64() {
+        assert_eq!(to_u32(u32::MAX as usize).unwrap(), u32::MAX);
+        #[cfg(target_pointer_width = "64")]
+        assert!(to_u32(u32::MAX as usize + 1).is_err());
+    }
+}